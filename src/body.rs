@@ -0,0 +1,178 @@
+//! Response's body reader shared by the buffered getters, the frame iterator and downloads.
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use http_body_util::BodyExt;
+use hyper::body::{Bytes, Incoming};
+use tokio::{
+    fs::{self, File, OpenOptions},
+    io::AsyncWriteExt,
+};
+
+use crate::{
+    encoding::Decoding,
+    error::{SilqError, Timeout},
+    timeout::within,
+};
+
+pub struct ResponseBody {
+    incoming: Incoming,
+    read_timeout: Option<Duration>,
+    deadline: Option<Instant>,
+    /// Decoder of the body's content codings, taken once the end of the body is reached.
+    decoding: Option<Decoding>,
+}
+
+impl ResponseBody {
+    pub fn new(
+        incoming: Incoming,
+        read_timeout: Option<Duration>,
+        deadline: Option<Instant>,
+        decoding: Option<Decoding>,
+    ) -> Self {
+        Self {
+            incoming,
+            read_timeout,
+            deadline,
+            decoding,
+        }
+    }
+
+    /// Wait for the next chunk of data, decoded if needed, skipping trailers. Returns `None` at
+    /// the end of the body.
+    pub async fn next_chunk(&mut self) -> Result<Option<Bytes>, SilqError> {
+        loop {
+            let next = within(
+                Timeout::Read,
+                self.read_timeout,
+                self.deadline,
+                self.incoming.frame(),
+            )
+            .await?;
+            match next {
+                None => {
+                    let rest = match self.decoding.take() {
+                        Some(mut decoding) => decoding.finish()?,
+                        None => vec![],
+                    };
+                    return Ok((!rest.is_empty()).then(|| Bytes::from(rest)));
+                }
+                Some(Err(err)) => return Err(SilqError::from("Unable to fetch next frame", &err)),
+                Some(Ok(frame)) => {
+                    let Ok(chunk) = frame.into_data() else {
+                        continue;
+                    };
+                    let chunk = match &mut self.decoding {
+                        Some(decoding) => Bytes::from(decoding.decode(&chunk)?),
+                        None => chunk,
+                    };
+                    // Decoders can need more data to produce anything
+                    if !chunk.is_empty() {
+                        return Ok(Some(chunk));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Read the remaining of the body.
+    pub async fn collect(&mut self) -> Result<Vec<u8>, SilqError> {
+        let mut content = vec![];
+        while let Some(chunk) = self.next_chunk().await? {
+            content.extend_from_slice(&chunk);
+        }
+        Ok(content)
+    }
+
+    /// Write the remaining of the body to the file at `path`, returning the number of bytes
+    /// written.
+    pub async fn save(&mut self, path: &Path, options: SaveOptions) -> Result<u64, SilqError> {
+        if options.atomic && options.append {
+            Err(SilqError::new(
+                "Atomic and append modes can't be combined".to_string(),
+            ))?
+        }
+        let context = format!("Unable to write file {}", path.display());
+
+        if !options.atomic {
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .append(options.append)
+                .truncate(!options.append)
+                .open(path)
+                .await
+                .map_err(|err| SilqError::from(&context, &err))?;
+            // Partial content is kept, e.g. to resume the download from where it stopped
+            return self.write_to(&mut file, &context, options.max_size).await;
+        }
+
+        // Written next to the target, so that renaming it doesn't cross file systems
+        let partial = partial_path(path);
+        let written = async {
+            let mut file = File::create(&partial)
+                .await
+                .map_err(|err| SilqError::from(&context, &err))?;
+            let written = self.write_to(&mut file, &context, options.max_size).await?;
+            file.sync_all()
+                .await
+                .map_err(|err| SilqError::from(&context, &err))?;
+            fs::rename(&partial, path)
+                .await
+                .map_err(|err| SilqError::from(&context, &err))?;
+            Ok::<_, SilqError>(written)
+        }
+        .await;
+        if written.is_err() {
+            let _ = fs::remove_file(&partial).await;
+        }
+        written
+    }
+
+    async fn write_to(
+        &mut self,
+        file: &mut File,
+        context: &str,
+        max_size: Option<u64>,
+    ) -> Result<u64, SilqError> {
+        let mut written = 0;
+        while let Some(chunk) = self.next_chunk().await? {
+            if let Some(max_size) = max_size.filter(|max| written + chunk.len() as u64 > *max) {
+                Err(SilqError::new(format!(
+                    "Body exceeds the maximum size of {max_size} bytes"
+                )))?
+            }
+            file.write_all(&chunk)
+                .await
+                .map_err(|err| SilqError::from(context, &err))?;
+            written += chunk.len() as u64;
+        }
+        file.flush()
+            .await
+            .map_err(|err| SilqError::from(context, &err))?;
+        Ok(written)
+    }
+}
+
+/// How `ResponseBody::save` writes the file.
+#[derive(Clone, Copy, Default)]
+pub struct SaveOptions {
+    /// Write to a temporary file renamed to the target once complete.
+    pub atomic: bool,
+    /// Append to the file instead of truncating it.
+    pub append: bool,
+    /// Maximum number of bytes to write, failing past it.
+    pub max_size: Option<u64>,
+}
+
+/// Hidden temporary file next to `path`.
+fn partial_path(path: &Path) -> PathBuf {
+    let random = RandomState::new().build_hasher().finish();
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{name}.{random:016x}.part"))
+}
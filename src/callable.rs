@@ -0,0 +1,54 @@
+//! PHP callables kept in client or request options, e.g. a DNS resolver.
+use ext_php_rs::{
+    convert::IntoZvalDyn,
+    types::{ZendCallable, ZendHashTable, Zval},
+};
+
+use crate::error::SilqError;
+
+/// Reference to a PHP callable, only callable from PHP's thread.
+pub struct Callable(Zval);
+
+impl Clone for Callable {
+    fn clone(&self) -> Self {
+        Self(self.0.shallow_clone())
+    }
+}
+
+impl Callable {
+    /// Keep a reference to `value`, described by `name` in errors.
+    pub fn new(value: &Zval, name: &str) -> Result<Self, SilqError> {
+        if !value.is_callable() {
+            Err(SilqError::new(format!("{name} must be callable")))?
+        }
+        Ok(Self(value.shallow_clone()))
+    }
+
+    /// Callable of `object`'s method, e.g. to drive a `Generator`.
+    pub fn method(object: &Zval, method: &str) -> Result<Self, SilqError> {
+        let error =
+            |err: ext_php_rs::error::Error| SilqError::from("Unable to reference method", &err);
+        let mut array = ZendHashTable::new();
+        array.push(object.shallow_clone()).map_err(error)?;
+        array.push(method).map_err(error)?;
+        let mut value = Zval::new();
+        value.set_hashtable(array);
+        Self::new(&value, method)
+    }
+
+    /// Callable of a PHP function, e.g. `fread`.
+    pub fn function(name: &str) -> Result<Self, SilqError> {
+        let mut value = Zval::new();
+        value
+            .set_string(name, false)
+            .map_err(|err| SilqError::from("Unable to reference function", &err))?;
+        Self::new(&value, name)
+    }
+
+    pub fn call(&self, name: &str, params: Vec<&dyn IntoZvalDyn>) -> Result<Zval, SilqError> {
+        ZendCallable::new(&self.0)
+            .map_err(|err| SilqError::from(&format!("Invalid {name}"), &err))?
+            .try_call(params)
+            .map_err(|err| SilqError::from(&format!("{name} failed"), &err))
+    }
+}
@@ -0,0 +1,43 @@
+//! Driving many requests at once from PHP's thread, where their callbacks have to run.
+use std::future::{poll_fn, Future};
+use std::task::Poll;
+use std::time::Instant;
+
+use crate::{error::Timeout, timeout::within};
+
+/// Poll the futures concurrently until they all complete, or the deadline is reached. Outputs are
+/// in the same order as the futures, `None` for those which didn't complete in time.
+pub async fn join_all<F: Future>(
+    futures: Vec<F>,
+    deadline: Option<Instant>,
+) -> Vec<Option<F::Output>> {
+    let mut futures = futures
+        .into_iter()
+        .map(|future| Some(Box::pin(future)))
+        .collect::<Vec<_>>();
+    let mut outputs = futures.iter().map(|_| None).collect::<Vec<_>>();
+
+    let all = poll_fn(|cx| {
+        let mut pending = false;
+        for (slot, output) in futures.iter_mut().zip(outputs.iter_mut()) {
+            let Some(future) = slot else {
+                continue;
+            };
+            match future.as_mut().poll(cx) {
+                Poll::Ready(value) => {
+                    *output = Some(value);
+                    *slot = None;
+                }
+                Poll::Pending => pending = true,
+            }
+        }
+        if pending {
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    });
+    // Futures still pending are dropped, cancelling them
+    let _ = within(Timeout::Deadline, None, deadline, all).await;
+    outputs
+}
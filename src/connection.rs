@@ -0,0 +1,174 @@
+//! Protocol-agnostic handle over established HTTP connections.
+use std::error::Error;
+use std::io;
+
+use http::{header::HOST, Request, Response, Uri, Version};
+use hyper::{
+    body::Incoming,
+    client::conn::{http1, http2},
+};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::{
+    error::{Failure, SilqError},
+    payload::RequestBody,
+};
+
+type Body = RequestBody;
+
+/// HTTP versions the client is allowed to negotiate.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum HttpVersionPolicy {
+    Http1Only,
+    Http2Preferred,
+    Http2Only,
+}
+
+impl HttpVersionPolicy {
+    pub fn parse(policy: &str) -> Result<Self, SilqError> {
+        match policy {
+            "http1" => Ok(Self::Http1Only),
+            "http2-preferred" => Ok(Self::Http2Preferred),
+            "http2" => Ok(Self::Http2Only),
+            _ => Err(SilqError::new(format!(
+                "Unknown HTTP version policy '{policy}', expected one of: http1, http2-preferred, http2"
+            ))),
+        }
+    }
+
+    /// Protocols advertised through TLS' ALPN extension, by order of preference.
+    pub fn alpn_protocols(&self) -> Vec<Vec<u8>> {
+        match self {
+            Self::Http1Only => vec![b"http/1.1".to_vec()],
+            Self::Http2Preferred => vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+            Self::Http2Only => vec![b"h2".to_vec()],
+        }
+    }
+}
+
+pub enum Sender {
+    Http1 {
+        sender: http1::SendRequest<Body>,
+        /// Whether requests are forwarded by a proxy, which expects them in absolute-form.
+        forward: bool,
+    },
+    Http2(http2::SendRequest<Body>),
+}
+
+impl Sender {
+    pub fn is_ready(&self) -> bool {
+        match self {
+            Sender::Http1 { sender, .. } => sender.is_ready(),
+            Sender::Http2(sender) => sender.is_ready(),
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        match self {
+            Sender::Http1 { sender, .. } => sender.is_closed(),
+            Sender::Http2(sender) => sender.is_closed(),
+        }
+    }
+
+    /// HTTP/2 connections multiplex requests, so they can be used concurrently.
+    pub fn is_multiplexed(&self) -> bool {
+        matches!(self, Sender::Http2(_))
+    }
+
+    /// Returns a new handle over the same connection if it is multiplexed.
+    pub fn share(&self) -> Option<Sender> {
+        match self {
+            Sender::Http1 { .. } => None,
+            Sender::Http2(sender) => Some(Sender::Http2(sender.clone())),
+        }
+    }
+
+    pub async fn ready(&mut self) -> Result<(), SilqError> {
+        match self {
+            Sender::Http1 { sender, .. } => sender.ready().await,
+            Sender::Http2(sender) => sender.ready().await,
+        }
+        .map_err(|err| SilqError::from("Connection unavailable", &err))
+    }
+
+    pub async fn send_request(
+        &mut self,
+        mut req: Request<Body>,
+    ) -> Result<Response<Incoming>, SilqError> {
+        match self {
+            Sender::Http1 { sender, forward } => {
+                // HTTP/1 servers expect the origin-form, e.g. `/path?query`
+                if !*forward {
+                    origin_form(req.uri_mut());
+                }
+                sender.send_request(req).await
+            }
+            Sender::Http2(sender) => {
+                // HTTP/2 conveys the host through the `:authority` pseudo-header
+                req.headers_mut().remove(HOST);
+                sender.send_request(req).await
+            }
+        }
+        .map_err(|err| {
+            let reset = err.is_closed()
+                || err.is_incomplete_message()
+                || err.source().is_some_and(|source| source.is::<io::Error>());
+            let error = SilqError::from("Unable to send request", &err);
+            // Canceled requests were dropped by the connection before being written
+            if err.is_canceled() {
+                error.with_failure(Failure::Unsent)
+            } else if reset {
+                error.with_failure(Failure::Reset)
+            } else {
+                error
+            }
+        })
+    }
+}
+
+fn origin_form(uri: &mut Uri) {
+    let path = match uri.path_and_query() {
+        Some(path) if path.as_str() != "/" => {
+            let mut parts = http::uri::Parts::default();
+            parts.path_and_query = Some(path.clone());
+            Uri::from_parts(parts).expect("path is a valid URI")
+        }
+        _ => Uri::default(),
+    };
+    *uri = path;
+}
+
+/// Perform the HTTP handshake over an established stream and spawn the task driving the connection.
+/// `forward` tells whether the stream is connected to a forward proxy rather than the origin.
+pub async fn handshake<T>(stream: T, version: Version, forward: bool) -> Result<Sender, SilqError>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let stream = TokioIo::new(stream);
+    if version == Version::HTTP_2 {
+        let (sender, conn) = http2::handshake(TokioExecutor::new(), stream)
+            .await
+            .map_err(|err| SilqError::from("Unable to run handshake", &err))?;
+
+        // spawn a task to poll the connection and drive the HTTP state
+        tokio::task::spawn(async move {
+            conn.await
+                .map_err(|err| SilqError::from("Unable to poll connection", &err))
+        });
+
+        Ok(Sender::Http2(sender))
+    } else {
+        let (sender, conn) = http1::handshake(stream)
+            .await
+            .map_err(|err| SilqError::from("Unable to run handshake", &err))?;
+
+        // spawn a task to poll the connection and drive the HTTP state
+        tokio::task::spawn(async move {
+            conn.await
+                .map_err(|err| SilqError::from("Unable to poll connection", &err))
+        });
+
+        Ok(Sender::Http1 { sender, forward })
+    }
+}
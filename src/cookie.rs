@@ -0,0 +1,742 @@
+//! Cookie storage (RFC 6265): cookies set by responses are kept in a `CookieJar` and sent back
+//! with the later requests matching their domain, path and security attributes.
+//!
+//! Sites and the domain attributes cookies can be set for follow the public suffix list, so that
+//! e.g. `Domain=co.uk` isn't shared by all the `.co.uk` sites.
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ext_php_rs::prelude::*;
+use http::{
+    header::{COOKIE, SET_COOKIE},
+    HeaderMap, HeaderValue, Method, Uri,
+};
+use serde_json::{json, Value};
+
+use crate::error::SilqError;
+
+/// Lifetime cookies are capped to, as browsers do (RFC 6265bis).
+const MAX_LIFETIME: Duration = Duration::from_secs(400 * 24 * 3600);
+
+const NETSCAPE_HEADER: &str = "# Netscape HTTP Cookie File";
+const NETSCAPE_HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl SameSite {
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "strict" => Some(Self::Strict),
+            "lax" => Some(Self::Lax),
+            "none" => Some(Self::None),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Strict => "Strict",
+            Self::Lax => "Lax",
+            Self::None => "None",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    /// Host the cookie was set by if `host_only`, otherwise the domain it is sent to, along with
+    /// its subdomains.
+    pub domain: String,
+    pub host_only: bool,
+    pub path: String,
+    /// None for session cookies.
+    pub expires: Option<SystemTime>,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: Option<SameSite>,
+    created: SystemTime,
+}
+
+/// Cookie as set by a `Set-Cookie` header, with its attributes.
+#[php_class(name = "Silq\\Cookie")]
+#[derive(Clone)]
+pub struct SetCookie {
+    name: String,
+    value: String,
+    expires: Option<SystemTime>,
+    max_age: Option<i64>,
+    domain: Option<String>,
+    path: Option<String>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+    partitioned: bool,
+}
+
+impl SetCookie {
+    /// Parse a `Set-Cookie` header leniently (RFC 6265 section 5.2), collecting the reasons for
+    /// ignoring the cookie or some of its attributes in `errors`.
+    pub fn parse(header: &str, errors: &mut Vec<String>) -> Option<Self> {
+        let (pair, attributes) = header.split_once(';').unwrap_or((header, ""));
+        let Some((name, value)) = pair.split_once('=') else {
+            errors.push(format!("Invalid cookie {:?}: missing '='", trim(pair)));
+            return None;
+        };
+        let (name, value) = (trim(name), trim(value));
+        if name.is_empty() {
+            errors.push(format!("Invalid cookie {:?}: empty name", trim(pair)));
+            return None;
+        }
+
+        let mut cookie = Self {
+            name: name.to_string(),
+            value: value.to_string(),
+            expires: None,
+            max_age: None,
+            domain: None,
+            path: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+            partitioned: false,
+        };
+        for attribute in attributes.split(';') {
+            let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+            let value = trim(value);
+            let invalid = |attribute: &str| {
+                format!("Invalid {attribute} attribute of cookie {name}: {value:?}")
+            };
+            match trim(key).to_ascii_lowercase().as_str() {
+                "expires" => match parse_date(value) {
+                    Some(expires) => cookie.expires = Some(expires),
+                    None => errors.push(invalid("Expires")),
+                },
+                "max-age" => match parse_max_age(value) {
+                    Some(max_age) => cookie.max_age = Some(max_age),
+                    None => errors.push(invalid("Max-Age")),
+                },
+                "domain" if !value.is_empty() => {
+                    cookie.domain = Some(value.trim_start_matches('.').to_ascii_lowercase())
+                }
+                "path" => {
+                    cookie.path = Some(value.to_string()).filter(|path| path.starts_with('/'))
+                }
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                "samesite" => match SameSite::parse(value) {
+                    Some(same_site) => cookie.same_site = Some(same_site),
+                    None => errors.push(invalid("SameSite")),
+                },
+                "partitioned" => cookie.partitioned = true,
+                _ => {}
+            }
+        }
+        Some(cookie)
+    }
+
+    /// Parse the `Set-Cookie` headers of a response, collecting errors instead of failing.
+    pub fn parse_all(headers: &HeaderMap, errors: &mut Vec<String>) -> Vec<Self> {
+        headers
+            .get_all(SET_COOKIE)
+            .iter()
+            .filter_map(|header| Self::parse(&String::from_utf8_lossy(header.as_bytes()), errors))
+            .collect()
+    }
+}
+
+#[php_impl]
+impl SetCookie {
+    pub fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn get_value(&self) -> String {
+        self.value.clone()
+    }
+
+    /// Returns the domain the cookie is also sent to the subdomains of, without leading dot.
+    pub fn get_domain(&self) -> Option<String> {
+        self.domain.clone()
+    }
+
+    /// Returns the path, if valid.
+    pub fn get_path(&self) -> Option<String> {
+        self.path.clone()
+    }
+
+    /// Returns the Expires attribute as a Unix timestamp.
+    pub fn get_expires(&self) -> Option<i64> {
+        self.expires.map(to_unix)
+    }
+
+    /// Returns the Max-Age attribute in seconds, which takes precedence over Expires.
+    pub fn get_max_age(&self) -> Option<i64> {
+        self.max_age
+    }
+
+    pub fn is_secure(&self) -> bool {
+        self.secure
+    }
+
+    pub fn is_http_only(&self) -> bool {
+        self.http_only
+    }
+
+    /// Returns Strict, Lax or None.
+    pub fn get_same_site(&self) -> Option<String> {
+        self.same_site.map(|same_site| same_site.name().to_string())
+    }
+
+    pub fn is_partitioned(&self) -> bool {
+        self.partitioned
+    }
+}
+
+impl Cookie {
+    /// Parse a `Set-Cookie` header received from `uri`, following the RFC 6265 storage model.
+    /// Returns None for cookies to ignore, and expired cookies for those to remove.
+    pub fn parse(header: &str, uri: &Uri, now: SystemTime) -> Option<Self> {
+        let SetCookie {
+            name,
+            value,
+            expires,
+            max_age,
+            domain,
+            path,
+            secure,
+            http_only,
+            same_site,
+            ..
+        } = SetCookie::parse(header, &mut vec![])?;
+
+        let host = uri.host()?.to_ascii_lowercase();
+        let expires = match max_age {
+            Some(seconds) if seconds <= 0 => Some(UNIX_EPOCH),
+            Some(seconds) => Some(now + MAX_LIFETIME.min(Duration::from_secs(seconds as u64))),
+            None => expires.map(|expires: SystemTime| expires.min(now + MAX_LIFETIME)),
+        };
+        let (domain, host_only) = match domain {
+            Some(domain) if domain == host => (domain, false),
+            Some(domain) if !domain_matches(&host, &domain) || is_public_suffix(&domain) => {
+                return None
+            }
+            Some(domain) => (domain, false),
+            None => (host, true),
+        };
+        let path = path.unwrap_or_else(|| default_path(uri.path()));
+
+        // Secure cookies can only be set by secure origins, and SameSite=None requires them
+        if (secure && !is_secure(uri)) || (same_site == Some(SameSite::None) && !secure) {
+            return None;
+        }
+        if name.starts_with("__Secure-") && !secure {
+            return None;
+        }
+        if name.starts_with("__Host-") && (!secure || !host_only || path != "/") {
+            return None;
+        }
+
+        Some(Self {
+            name,
+            value,
+            domain,
+            host_only,
+            path,
+            expires,
+            secure,
+            http_only,
+            same_site,
+            created: now,
+        })
+    }
+
+    fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    /// Whether the cookie is sent with a request to `uri`, `cross_site` or not.
+    fn matches(&self, uri: &Uri, method: &Method, cross_site: bool) -> bool {
+        let host = uri.host().unwrap_or_default().to_ascii_lowercase();
+        let domain_matches = match self.host_only {
+            true => host == self.domain,
+            false => domain_matches(&host, &self.domain),
+        };
+        let same_site_allows = match self.same_site {
+            _ if !cross_site => true,
+            Some(SameSite::Strict) => false,
+            Some(SameSite::Lax) => method.is_safe(),
+            Some(SameSite::None) | None => true,
+        };
+        domain_matches
+            && path_matches(uri.path(), &self.path)
+            && (!self.secure || is_secure(uri))
+            && same_site_allows
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "value": self.value,
+            "domain": self.domain,
+            "hostOnly": self.host_only,
+            "path": self.path,
+            "expires": self.expires.map(to_unix),
+            "secure": self.secure,
+            "httpOnly": self.http_only,
+            "sameSite": self.same_site.map(|same_site| same_site.name()),
+            "created": to_unix(self.created),
+        })
+    }
+
+    fn from_json(value: &Value, now: SystemTime) -> Result<Self, SilqError> {
+        let string = |field: &str| {
+            value[field]
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| SilqError::new(format!("Invalid cookie: missing {field}")))
+        };
+        let flag = |field: &str| value[field].as_bool().unwrap_or(false);
+        Ok(Self {
+            name: string("name")?,
+            value: string("value")?,
+            domain: string("domain")?.to_ascii_lowercase(),
+            host_only: flag("hostOnly"),
+            path: string("path").unwrap_or_else(|_| "/".to_string()),
+            expires: value["expires"].as_i64().map(from_unix),
+            secure: flag("secure"),
+            http_only: flag("httpOnly"),
+            same_site: value["sameSite"].as_str().and_then(SameSite::parse),
+            created: value["created"].as_i64().map_or(now, from_unix),
+        })
+    }
+
+    fn to_netscape(&self) -> String {
+        let flag = |value: bool| if value { "TRUE" } else { "FALSE" };
+        format!(
+            "{}{}{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            if self.http_only {
+                NETSCAPE_HTTP_ONLY_PREFIX
+            } else {
+                ""
+            },
+            if self.host_only { "" } else { "." },
+            self.domain,
+            flag(!self.host_only),
+            self.path,
+            flag(self.secure),
+            self.expires.map_or(0, to_unix),
+            self.name,
+            self.value,
+        )
+    }
+
+    fn from_netscape(line: &str, now: SystemTime) -> Option<Self> {
+        let (line, http_only) = match line.strip_prefix(NETSCAPE_HTTP_ONLY_PREFIX) {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+        let [domain, include_subdomains, path, secure, expires, name, value] =
+            <[&str; 7]>::try_from(line.split('\t').collect::<Vec<_>>()).ok()?;
+        let expires = expires.parse::<i64>().ok()?;
+        Some(Self {
+            name: name.to_string(),
+            value: value.to_string(),
+            domain: domain.trim_start_matches('.').to_ascii_lowercase(),
+            host_only: !include_subdomains.eq_ignore_ascii_case("TRUE"),
+            path: path.to_string(),
+            expires: Some(expires).filter(|expires| *expires != 0).map(from_unix),
+            secure: secure.eq_ignore_ascii_case("TRUE"),
+            http_only,
+            same_site: None,
+            created: now,
+        })
+    }
+}
+
+/// Cookies shared by the clients they are given to, which store the cookies set by responses and
+/// send them back with later requests, redirects included.
+///
+/// Can be exported and imported as JSON or in the Netscape format used by curl, e.g. to keep
+/// cookies across PHP requests.
+#[php_class(name = "Silq\\CookieJar")]
+#[derive(Clone, Default)]
+pub struct CookieJar {
+    cookies: Arc<Mutex<Vec<Cookie>>>,
+}
+
+impl CookieJar {
+    fn cookies(&self) -> MutexGuard<Vec<Cookie>> {
+        self.cookies.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn with_cookies(cookies: Vec<Cookie>) -> Self {
+        let jar = Self::default();
+        for cookie in cookies {
+            jar.insert(cookie, true);
+        }
+        jar
+    }
+
+    /// Store a cookie, replacing the one with the same name, domain and path.
+    fn insert(&self, mut cookie: Cookie, from_secure: bool) {
+        let now = SystemTime::now();
+        let mut cookies = self.cookies();
+        // Unsecure origins can't shadow secure cookies
+        let shadows_secure = |existing: &Cookie| {
+            existing.secure
+                && existing.name == cookie.name
+                && (domain_matches(&cookie.domain, &existing.domain)
+                    || domain_matches(&existing.domain, &cookie.domain))
+                && path_matches(&cookie.path, &existing.path)
+        };
+        if !from_secure && cookies.iter().any(shadows_secure) {
+            return;
+        }
+        if let Some(index) = cookies.iter().position(|existing| {
+            existing.name == cookie.name
+                && existing.domain == cookie.domain
+                && existing.path == cookie.path
+        }) {
+            cookie.created = cookies.remove(index).created;
+        }
+        if !cookie.is_expired(now) {
+            cookies.push(cookie);
+        }
+    }
+
+    /// Store the cookies set by a response received from `uri`.
+    pub fn store(&self, uri: &Uri, headers: &HeaderMap) {
+        let now = SystemTime::now();
+        for header in headers.get_all(SET_COOKIE) {
+            let cookie = header
+                .to_str()
+                .ok()
+                .and_then(|header| Cookie::parse(header, uri, now));
+            if let Some(cookie) = cookie {
+                self.insert(cookie, is_secure(uri));
+            }
+        }
+    }
+
+    /// Value of the `Cookie` header to send to `uri`, for a request initiated on `initiator`.
+    fn header(&self, uri: &Uri, method: &Method, initiator: &Uri) -> Option<String> {
+        let now = SystemTime::now();
+        let cross_site = site(uri) != site(initiator);
+        let mut cookies = self.cookies();
+        cookies.retain(|cookie| !cookie.is_expired(now));
+        let mut matching = cookies
+            .iter()
+            .filter(|cookie| cookie.matches(uri, method, cross_site))
+            .collect::<Vec<_>>();
+        // Longer paths first, then older cookies first
+        matching.sort_by(|a, b| {
+            b.path
+                .len()
+                .cmp(&a.path.len())
+                .then(a.created.cmp(&b.created))
+        });
+        let header = matching
+            .iter()
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        Some(header).filter(|header| !header.is_empty())
+    }
+
+    /// Add the cookies to send to `uri` to the given headers, after those set on the request.
+    pub fn apply(
+        &self,
+        headers: &HeaderMap,
+        method: &Method,
+        uri: &Uri,
+        initiator: &Uri,
+    ) -> Result<HeaderMap, SilqError> {
+        let mut headers = headers.clone();
+        if let Some(cookies) = self.header(uri, method, initiator) {
+            let value = match headers.get(COOKIE).and_then(|value| value.to_str().ok()) {
+                Some(existing) => format!("{existing}; {cookies}"),
+                None => cookies,
+            };
+            headers.insert(
+                COOKIE,
+                HeaderValue::try_from(value)
+                    .map_err(|err| SilqError::from("Invalid cookie", &err))?,
+            );
+        }
+        Ok(headers)
+    }
+}
+
+#[php_impl]
+impl CookieJar {
+    #[constructor]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Import cookies exported with `toJson()`.
+    ///
+    /// @param json string
+    /// @return CookieJar
+    pub fn from_json(json: &str) -> PhpResult<Self> {
+        let now = SystemTime::now();
+        let values = serde_json::from_str::<Vec<Value>>(json)
+            .map_err(|err| SilqError::from("Invalid cookie jar JSON", &err))?;
+        let cookies = values
+            .iter()
+            .map(|value| Cookie::from_json(value, now))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::with_cookies(cookies))
+    }
+
+    /// Export the cookies, session ones included, as a JSON array.
+    pub fn to_json(&self) -> PhpResult<String> {
+        let now = SystemTime::now();
+        let cookies = self
+            .cookies()
+            .iter()
+            .filter(|cookie| !cookie.is_expired(now))
+            .map(Cookie::to_json)
+            .collect::<Vec<_>>();
+        Ok(serde_json::to_string(&cookies)
+            .map_err(|err| SilqError::from("Unable to encode cookies", &err))?)
+    }
+
+    /// Import cookies in the Netscape format used by curl and wget.
+    ///
+    /// @param contents string
+    /// @return CookieJar
+    pub fn from_netscape(contents: &str) -> PhpResult<Self> {
+        let now = SystemTime::now();
+        let mut cookies = vec![];
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim_end_matches('\r');
+            if line.trim().is_empty()
+                || (line.starts_with('#') && !line.starts_with(NETSCAPE_HTTP_ONLY_PREFIX))
+            {
+                continue;
+            }
+            cookies.push(Cookie::from_netscape(line, now).ok_or_else(|| {
+                SilqError::new(format!("Invalid Netscape cookie on line {}", number + 1))
+            })?);
+        }
+        Ok(Self::with_cookies(cookies))
+    }
+
+    /// Export the cookies in the Netscape format used by curl and wget. SameSite attributes are
+    /// lost, session cookies expire at 0.
+    pub fn to_netscape(&self) -> String {
+        let now = SystemTime::now();
+        let mut contents = format!("{NETSCAPE_HEADER}\n");
+        for cookie in self
+            .cookies()
+            .iter()
+            .filter(|cookie| !cookie.is_expired(now))
+        {
+            contents.push_str(&cookie.to_netscape());
+            contents.push('\n');
+        }
+        contents
+    }
+
+    /// Returns the `Cookie` header sent with a request to the given URI, if any.
+    ///
+    /// @param uri string
+    /// @return string|null
+    pub fn get_cookie_header(&self, uri: &str) -> PhpResult<Option<String>> {
+        let uri = uri
+            .parse::<Uri>()
+            .map_err(|err| SilqError::from("Unable to parse URI", &err))?;
+        Ok(self.header(&uri, &Method::GET, &uri))
+    }
+
+    /// Returns the number of cookies stored.
+    pub fn count(&self) -> usize {
+        let now = SystemTime::now();
+        self.cookies()
+            .iter()
+            .filter(|cookie| !cookie.is_expired(now))
+            .count()
+    }
+
+    /// Remove all cookies.
+    pub fn clear(&self) {
+        self.cookies().clear();
+    }
+}
+
+fn trim(value: &str) -> &str {
+    value.trim_matches([' ', '\t'])
+}
+
+fn is_secure(uri: &Uri) -> bool {
+    uri.scheme_str() == Some("https")
+}
+
+/// Whether `host` is `domain` or one of its subdomains (RFC 6265 section 5.1.3).
+fn domain_matches(host: &str, domain: &str) -> bool {
+    host == domain
+        || (host.ends_with(domain)
+            && host[..host.len() - domain.len()].ends_with('.')
+            && host.trim_matches(['[', ']']).parse::<IpAddr>().is_err())
+}
+
+/// Directory of the request path (RFC 6265 section 5.1.4).
+fn default_path(path: &str) -> String {
+    match path.rfind('/') {
+        Some(index) if index > 0 && path.starts_with('/') => path[..index].to_string(),
+        _ => "/".to_string(),
+    }
+}
+
+/// Whether `path` is in `cookie_path` (RFC 6265 section 5.1.4).
+fn path_matches(path: &str, cookie_path: &str) -> bool {
+    path == cookie_path
+        || (path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || path[cookie_path.len()..].starts_with('/')))
+}
+
+/// Whether `domain` is shared by unrelated sites, e.g. `com`, `co.uk` or `github.io`, so that
+/// cookies can't be set for it. Single labels are considered public suffixes.
+fn is_public_suffix(domain: &str) -> bool {
+    !domain.contains('.') || psl::suffix_str(domain) == Some(domain)
+}
+
+/// Scheme and registrable domain, according to the public suffix list. Hosts without one, e.g.
+/// IP addresses or public suffixes themselves, are their own site.
+fn site(uri: &Uri) -> (Option<&str>, String) {
+    let host = uri.host().unwrap_or_default().to_ascii_lowercase();
+    if host.trim_matches(['[', ']']).parse::<IpAddr>().is_ok() {
+        return (uri.scheme_str(), host);
+    }
+    let site = psl::domain_str(&host).map_or_else(|| host.clone(), str::to_string);
+    (uri.scheme_str(), site)
+}
+
+fn parse_max_age(value: &str) -> Option<i64> {
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    // Overflowing ages are as good as infinite
+    Some(
+        value
+            .parse::<i64>()
+            .unwrap_or(match value.starts_with('-') {
+                true => i64::MIN,
+                false => i64::MAX,
+            }),
+    )
+}
+
+/// Parse a cookie date (RFC 6265 section 5.1.1), lenient with the various formats in use.
+fn parse_date(value: &str) -> Option<SystemTime> {
+    let is_delimiter = |c: char| matches!(c, '\t' | ' '..='/' | ';'..='@' | '['..='`' | '{'..='~');
+    let (mut time, mut day, mut month, mut year) = (None, None, None, None);
+    for token in value.split(is_delimiter).filter(|token| !token.is_empty()) {
+        if time.is_none() {
+            if let Some(parsed) = parse_time(token) {
+                time = Some(parsed);
+                continue;
+            }
+        }
+        if day.is_none() {
+            if let Some(parsed) = leading_number(token, 1, 2) {
+                day = Some(parsed);
+                continue;
+            }
+        }
+        if month.is_none() {
+            if let Some(parsed) = parse_month(token) {
+                month = Some(parsed);
+                continue;
+            }
+        }
+        if year.is_none() {
+            if let Some(parsed) = leading_number(token, 2, 4) {
+                year = Some(parsed);
+                continue;
+            }
+        }
+    }
+
+    let year = match year? {
+        year @ 70..=99 => year + 1900,
+        year @ 0..=69 => year + 2000,
+        year => year,
+    };
+    let ((hour, minute, second), day, month) = (time?, day?, month?);
+    if !(1..=31).contains(&day) || year < 1601 || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    let seconds = days_from_civil(year.into(), month, day) * 86400
+        + i64::from(hour * 3600 + minute * 60 + second);
+    Some(from_unix(seconds))
+}
+
+/// Number of 1 to 2 digits in each of the hours, minutes and seconds.
+fn parse_time(token: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = token.splitn(3, ':');
+    let hour = parts.next()?;
+    let minute = parts.next()?;
+    let second = parts.next()?;
+    if !hour.bytes().all(|byte| byte.is_ascii_digit())
+        || !minute.bytes().all(|byte| byte.is_ascii_digit())
+    {
+        return None;
+    }
+    Some((
+        leading_number(hour, 1, 2)?,
+        leading_number(minute, 1, 2)?,
+        leading_number(second, 1, 2)?,
+    ))
+}
+
+/// Number made of the `min` to `max` digits the token starts with.
+fn leading_number(token: &str, min: usize, max: usize) -> Option<u32> {
+    let digits = token.bytes().take_while(u8::is_ascii_digit).count();
+    if !(min..=max).contains(&digits) {
+        return None;
+    }
+    token[..digits].parse().ok()
+}
+
+fn parse_month(token: &str) -> Option<u32> {
+    let prefix = token.get(..3)?.to_ascii_lowercase();
+    let months = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    months
+        .iter()
+        .position(|month| *month == prefix)
+        .map(|index| index as u32 + 1)
+}
+
+/// Days since the Unix epoch of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn to_unix(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() as i64)
+}
+
+fn from_unix(seconds: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(seconds.max(0) as u64)
+}
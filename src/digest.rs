@@ -0,0 +1,365 @@
+//! HTTP Digest access authentication (RFC 7616): `401` challenges are answered with the
+//! strongest supported algorithm, and their nonces reused for later requests to the same origin.
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use http::{header::WWW_AUTHENTICATE, HeaderMap, HeaderValue, Method, Uri};
+use md5::Md5;
+use sha2::{Digest, Sha256, Sha512_256};
+
+use crate::{error::SilqError, payload::Payload, redirect};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Algorithm {
+    Md5,
+    Sha256,
+    Sha512_256,
+}
+
+impl Algorithm {
+    /// Algorithm and whether it is a session variant, e.g. `SHA-256-sess`.
+    fn parse(name: &str) -> Option<(Self, bool)> {
+        let name = name.to_ascii_uppercase();
+        let (name, session) = match name.strip_suffix("-SESS") {
+            Some(name) => (name, true),
+            None => (name.as_str(), false),
+        };
+        let algorithm = match name {
+            "MD5" => Self::Md5,
+            "SHA-256" => Self::Sha256,
+            "SHA-512-256" => Self::Sha512_256,
+            _ => return None,
+        };
+        Some((algorithm, session))
+    }
+
+    fn name(&self, session: bool) -> String {
+        let name = match self {
+            Self::Md5 => "MD5",
+            Self::Sha256 => "SHA-256",
+            Self::Sha512_256 => "SHA-512-256",
+        };
+        match session {
+            true => format!("{name}-sess"),
+            false => name.to_string(),
+        }
+    }
+
+    fn hash(&self, data: impl AsRef<[u8]>) -> String {
+        let digest = match self {
+            Self::Md5 => Md5::digest(data).to_vec(),
+            Self::Sha256 => Sha256::digest(data).to_vec(),
+            Self::Sha512_256 => Sha512_256::digest(data).to_vec(),
+        };
+        digest.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Qop {
+    Auth,
+    AuthInt,
+}
+
+#[derive(Clone)]
+struct Challenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    algorithm: Algorithm,
+    session: bool,
+    /// Quality of protection chosen among the offered ones, None for RFC 2069 challenges.
+    qop: Option<Qop>,
+    stale: bool,
+    userhash: bool,
+}
+
+impl Challenge {
+    /// Strongest supported Digest challenge of a `401` response.
+    fn select(headers: &HeaderMap) -> Option<Self> {
+        headers
+            .get_all(WWW_AUTHENTICATE)
+            .iter()
+            .filter_map(|header| header.to_str().ok())
+            .flat_map(parse_challenges)
+            .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("Digest"))
+            .filter_map(|(_, params)| Self::parse(&params))
+            .max_by_key(|challenge| challenge.algorithm)
+    }
+
+    fn parse(params: &HashMap<String, String>) -> Option<Self> {
+        let (algorithm, session) = match params.get("algorithm") {
+            Some(algorithm) => Algorithm::parse(algorithm)?,
+            None => (Algorithm::Md5, false),
+        };
+        let qop = match params.get("qop") {
+            None => None,
+            Some(qop) => {
+                let offered = qop.split(',').map(str::trim).collect::<Vec<_>>();
+                if offered.iter().any(|qop| qop.eq_ignore_ascii_case("auth")) {
+                    Some(Qop::Auth)
+                } else if offered
+                    .iter()
+                    .any(|qop| qop.eq_ignore_ascii_case("auth-int"))
+                {
+                    Some(Qop::AuthInt)
+                } else {
+                    return None;
+                }
+            }
+        };
+        let flag = |name: &str| {
+            params
+                .get(name)
+                .is_some_and(|value| value.eq_ignore_ascii_case("true"))
+        };
+        Some(Self {
+            realm: params.get("realm")?.clone(),
+            nonce: params.get("nonce")?.clone(),
+            opaque: params.get("opaque").cloned(),
+            algorithm,
+            session,
+            qop,
+            stale: flag("stale"),
+            userhash: flag("userhash"),
+        })
+    }
+}
+
+struct Session {
+    challenge: Challenge,
+    /// Number of requests made with the challenge's nonce.
+    count: u32,
+}
+
+/// Nonces of the challenges answered, by origin and user, shared by a client and its clones.
+#[derive(Clone, Default)]
+pub struct DigestSessions(Arc<Mutex<HashMap<String, Session>>>);
+
+#[derive(Clone)]
+pub struct DigestAuth {
+    user: String,
+    password: String,
+}
+
+impl DigestAuth {
+    pub fn new(user: &str, password: &str) -> Self {
+        Self {
+            user: user.to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    fn key(&self, uri: &Uri) -> String {
+        let (scheme, host, port) = redirect::origin(uri);
+        format!(
+            "{}://{}:{} {}",
+            scheme.unwrap_or_default(),
+            host.unwrap_or_default(),
+            port.unwrap_or_default(),
+            self.user
+        )
+    }
+
+    /// `Authorization` header reusing the nonce of the last challenge answered for the origin,
+    /// if any.
+    pub fn preemptive(
+        &self,
+        sessions: &DigestSessions,
+        request: (&Method, &Uri, &Payload),
+    ) -> Result<Option<HeaderValue>, SilqError> {
+        let mut sessions = sessions.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(session) = sessions.get_mut(&self.key(request.1)) else {
+            return Ok(None);
+        };
+        session.count += 1;
+        self.authorization(&session.challenge, session.count, request)
+            .map(Some)
+    }
+
+    /// `Authorization` header answering the challenge of a `401` response, along with whether the
+    /// challenge only renews a stale nonce. None without supported challenge.
+    pub fn answer(
+        &self,
+        sessions: &DigestSessions,
+        headers: &HeaderMap,
+        request: (&Method, &Uri, &Payload),
+    ) -> Result<Option<(HeaderValue, bool)>, SilqError> {
+        let Some(challenge) = Challenge::select(headers) else {
+            return Ok(None);
+        };
+        let authorization = self.authorization(&challenge, 1, request)?;
+        let stale = challenge.stale;
+        sessions
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(
+                self.key(request.1),
+                Session {
+                    challenge,
+                    count: 1,
+                },
+            );
+        Ok(Some((authorization, stale)))
+    }
+
+    fn authorization(
+        &self,
+        challenge: &Challenge,
+        count: u32,
+        (method, uri, payload): (&Method, &Uri, &Payload),
+    ) -> Result<HeaderValue, SilqError> {
+        let algorithm = challenge.algorithm;
+        let digest_uri = uri
+            .path_and_query()
+            .map_or_else(|| "/".to_string(), ToString::to_string);
+        let random = [(); 2].map(|_| RandomState::new().build_hasher().finish());
+        let cnonce = format!("{:016x}{:016x}", random[0], random[1]);
+        let nc = format!("{count:08x}");
+
+        let mut ha1 = algorithm.hash(format!(
+            "{}:{}:{}",
+            self.user, challenge.realm, self.password
+        ));
+        if challenge.session {
+            ha1 = algorithm.hash(format!("{ha1}:{}:{cnonce}", challenge.nonce));
+        }
+        let ha2 = match challenge.qop {
+            Some(Qop::AuthInt) => {
+                let body = payload.to_bytes().ok_or_else(|| {
+                    SilqError::new(
+                        "Digest authentication with integrity protection requires an in-memory body"
+                            .to_string(),
+                    )
+                })?;
+                algorithm.hash(format!("{method}:{digest_uri}:{}", algorithm.hash(body)))
+            }
+            _ => algorithm.hash(format!("{method}:{digest_uri}")),
+        };
+        let response = match challenge.qop {
+            Some(qop) => algorithm.hash(format!(
+                "{ha1}:{}:{nc}:{cnonce}:{}:{ha2}",
+                challenge.nonce,
+                qop_name(qop)
+            )),
+            None => algorithm.hash(format!("{ha1}:{}:{ha2}", challenge.nonce)),
+        };
+
+        let mut params = vec![];
+        if challenge.userhash {
+            let user = algorithm.hash(format!("{}:{}", self.user, challenge.realm));
+            params.push(format!("username={}", quote(&user)));
+        } else if self.user.is_ascii() {
+            params.push(format!("username={}", quote(&self.user)));
+        } else {
+            params.push(format!(
+                "username*=UTF-8''{}",
+                urlencoding::encode(&self.user)
+            ));
+        }
+        params.push(format!("realm={}", quote(&challenge.realm)));
+        params.push(format!("uri={}", quote(&digest_uri)));
+        params.push(format!("algorithm={}", algorithm.name(challenge.session)));
+        params.push(format!("nonce={}", quote(&challenge.nonce)));
+        if let Some(qop) = challenge.qop {
+            params.push(format!("qop={}", qop_name(qop)));
+            params.push(format!("nc={nc}"));
+            params.push(format!("cnonce={}", quote(&cnonce)));
+        }
+        params.push(format!("response={}", quote(&response)));
+        if let Some(opaque) = &challenge.opaque {
+            params.push(format!("opaque={}", quote(opaque)));
+        }
+        if challenge.userhash {
+            params.push("userhash=true".to_string());
+        }
+
+        HeaderValue::try_from(format!("Digest {}", params.join(", ")))
+            .map_err(|err| SilqError::from("Unable to encode digest authorization", &err))
+    }
+}
+
+fn qop_name(qop: Qop) -> &'static str {
+    match qop {
+        Qop::Auth => "auth",
+        Qop::AuthInt => "auth-int",
+    }
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Parse the challenges of a `WWW-Authenticate` header (RFC 9110 section 11.6.1), each being a
+/// scheme and its lowercased parameters.
+fn parse_challenges(header: &str) -> Vec<(String, HashMap<String, String>)> {
+    let mut challenges: Vec<(String, HashMap<String, String>)> = vec![];
+    for item in split_unquoted(header, ',') {
+        let item = item.trim();
+        if item.is_empty() {
+            continue;
+        }
+        // A new challenge starts with its scheme, possibly followed by a first parameter
+        let param = match item.split_once(' ') {
+            Some((scheme, rest)) if !scheme.contains('=') => {
+                challenges.push((scheme.to_string(), HashMap::new()));
+                rest.trim()
+            }
+            None if !item.contains('=') => {
+                challenges.push((item.to_string(), HashMap::new()));
+                continue;
+            }
+            _ => item,
+        };
+        let (Some((_, params)), Some((name, value))) =
+            (challenges.last_mut(), param.split_once('='))
+        else {
+            continue;
+        };
+        params.insert(name.trim().to_ascii_lowercase(), unquote(value.trim()));
+    }
+    challenges
+}
+
+/// Split on `separator` outside of quoted strings.
+fn split_unquoted(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = vec![];
+    let (mut start, mut quoted, mut escaped) = (0, false, false);
+    for (index, char) in value.char_indices() {
+        match char {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            _ if char == separator && !quoted => {
+                parts.push(&value[start..index]);
+                start = index + char.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+fn unquote(value: &str) -> String {
+    match value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    {
+        Some(value) => {
+            let mut unquoted = String::with_capacity(value.len());
+            let mut chars = value.chars();
+            while let Some(char) = chars.next() {
+                match char {
+                    '\\' => unquoted.extend(chars.next()),
+                    char => unquoted.push(char),
+                }
+            }
+            unquoted
+        }
+        None => value.to_string(),
+    }
+}
@@ -0,0 +1,190 @@
+//! Host name resolution: static overrides, PHP callable resolver and a cache in front of the
+//! system resolver. Only the address connected to changes, TLS still uses the original host name.
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use ext_php_rs::types::Zval;
+use tokio::net::lookup_host;
+
+use crate::{callable::Callable, error::SilqError};
+
+#[derive(Clone)]
+struct CacheEntry {
+    addresses: Vec<IpAddr>,
+    expires_at: Instant,
+}
+
+/// Resolved addresses by host name, shared by clones of a client.
+#[derive(Clone)]
+struct DnsCache {
+    ttl: Duration,
+    entries: Arc<Mutex<HashMap<String, CacheEntry>>>,
+}
+
+impl DnsCache {
+    fn get(&self, host: &str) -> Option<Vec<IpAddr>> {
+        let entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries
+            .get(host)
+            .filter(|entry| entry.expires_at > Instant::now())
+            .map(|entry| entry.addresses.clone())
+    }
+
+    fn insert(&self, host: &str, addresses: &[IpAddr]) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.retain(|_, entry| entry.expires_at > now);
+        entries.insert(
+            host.to_string(),
+            CacheEntry {
+                addresses: addresses.to_vec(),
+                expires_at: now + self.ttl,
+            },
+        );
+    }
+}
+
+/// Resolve `host` with a PHP callable returning an IP address, a list of them, or null to fall
+/// back to the system resolver.
+fn call_resolver(resolver: &Callable, host: &str) -> Result<Option<Vec<IpAddr>>, SilqError> {
+    let result = resolver.call("DNS resolver", vec![&host.to_string()])?;
+
+    if result.is_null() {
+        return Ok(None);
+    }
+    let addresses = if let Some(address) = result.str() {
+        vec![parse_ip(address)?]
+    } else if let Some(array) = result.array() {
+        array
+            .values()
+            .map(|value| {
+                value.str().map(parse_ip).unwrap_or_else(|| {
+                    Err(SilqError::new(
+                        "DNS resolver must return IP addresses as strings".to_string(),
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        Err(SilqError::new(
+            "DNS resolver must return a string, an array of strings or null".to_string(),
+        ))?
+    };
+
+    if addresses.is_empty() {
+        Err(SilqError::new(format!(
+            "DNS resolver returned no address for {host}"
+        )))?
+    }
+    Ok(Some(addresses))
+}
+
+pub fn parse_ip(address: &str) -> Result<IpAddr, SilqError> {
+    address
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+        .map_err(|err| SilqError::from("Invalid IP address", &err))
+}
+
+#[derive(Clone, Default)]
+pub struct Resolver {
+    callback: Option<Callable>,
+    lookup: Lookup,
+}
+
+impl Resolver {
+    /// Always resolve `host` to the given addresses.
+    pub fn with_override(&mut self, host: &str, addresses: Vec<IpAddr>) {
+        self.lookup.overrides.insert(host.to_lowercase(), addresses);
+    }
+
+    /// Resolve host names with the given PHP callable before querying the system.
+    pub fn with_callback(&mut self, callable: &Zval) -> Result<(), SilqError> {
+        self.callback = Some(Callable::new(callable, "DNS resolver")?);
+        Ok(())
+    }
+
+    /// Keep resolved addresses for `ttl`, `None` disables caching.
+    pub fn with_cache_ttl(&mut self, ttl: Option<Duration>) {
+        self.lookup.cache = ttl.map(|ttl| DnsCache {
+            ttl,
+            entries: Default::default(),
+        });
+    }
+
+    /// Resolution of host names which can leave PHP's thread. The PHP callable, if any, is called
+    /// beforehand for `hosts`, its addresses taking precedence over the system's ones.
+    pub fn lookup(&self, hosts: &[&str]) -> Result<Lookup, SilqError> {
+        let mut lookup = self.lookup.clone();
+        let Some(callback) = &self.callback else {
+            return Ok(lookup);
+        };
+        for host in hosts {
+            let host = host.to_lowercase();
+            if parse_ip(&host).is_ok() || lookup.known(&host).is_some() {
+                continue;
+            }
+            if let Some(addresses) = call_resolver(callback, &host)? {
+                if let Some(cache) = &lookup.cache {
+                    cache.insert(&host, &addresses);
+                }
+                lookup.overrides.insert(host, addresses);
+            }
+        }
+        Ok(lookup)
+    }
+}
+
+/// Resolution of host names from the overrides, the cache, then the system resolver.
+#[derive(Clone, Default)]
+pub struct Lookup {
+    overrides: HashMap<String, Vec<IpAddr>>,
+    cache: Option<DnsCache>,
+}
+
+impl Lookup {
+    /// Socket addresses to try, in order, to reach `host` on `port`.
+    pub async fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, SilqError> {
+        let addresses = self.resolve_host(host).await?;
+        Ok(addresses
+            .into_iter()
+            .map(|ip| SocketAddr::new(ip, port))
+            .collect())
+    }
+
+    async fn resolve_host(&self, host: &str) -> Result<Vec<IpAddr>, SilqError> {
+        if let Ok(ip) = parse_ip(host) {
+            return Ok(vec![ip]);
+        }
+
+        let host = host.to_lowercase();
+        if let Some(addresses) = self.known(&host) {
+            return Ok(addresses);
+        }
+
+        let addresses = lookup_host((host.as_str(), 0))
+            .await
+            .map_err(|err| SilqError::from("Unable to resolve host", &err))?
+            .map(|address| address.ip())
+            .collect::<Vec<_>>();
+        if addresses.is_empty() {
+            Err(SilqError::new(format!("No address found for {host}")))?
+        }
+
+        if let Some(cache) = &self.cache {
+            cache.insert(&host, &addresses);
+        }
+        Ok(addresses)
+    }
+
+    /// Addresses of the lowercase `host` known without querying the system.
+    fn known(&self, host: &str) -> Option<Vec<IpAddr>> {
+        match self.overrides.get(host) {
+            Some(addresses) => Some(addresses.clone()),
+            None => self.cache.as_ref().and_then(|cache| cache.get(host)),
+        }
+    }
+}
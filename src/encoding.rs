@@ -0,0 +1,304 @@
+//! Content codings of HTTP bodies (RFC 9110 section 8.4.1), decoded as the body streams in, and
+//! encoded for requests.
+use std::io::{self, Write};
+use std::mem;
+
+use flate2::write::{DeflateDecoder, GzDecoder, GzEncoder, ZlibDecoder, ZlibEncoder};
+use http::{header::CONTENT_ENCODING, HeaderMap, HeaderValue};
+
+use crate::error::SilqError;
+
+/// Value of the `Accept-Encoding` header sent when decompression is enabled.
+pub static ACCEPT_ENCODING: HeaderValue = HeaderValue::from_static("gzip, deflate, br, zstd");
+
+const BROTLI_BUFFER_SIZE: usize = 4096;
+/// Brotli's recommended window size, in bits.
+const BROTLI_WINDOW_BITS: u32 = 22;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Gzip,
+    Deflate,
+    Brotli,
+    Zstd,
+}
+
+impl Encoding {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "gzip" | "x-gzip" => Some(Self::Gzip),
+            "deflate" => Some(Self::Deflate),
+            "br" => Some(Self::Brotli),
+            "zstd" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Deflate => "deflate",
+            Self::Brotli => "br",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    /// Codings applied to a body, in the order they were applied. `None` if one of them is unknown.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Vec<Self>> {
+        let mut encodings = vec![];
+        for value in headers.get_all(CONTENT_ENCODING) {
+            for name in value.to_str().ok()?.split(',') {
+                if name.trim().is_empty() || name.trim().eq_ignore_ascii_case("identity") {
+                    continue;
+                }
+                encodings.push(Self::parse(name)?);
+            }
+        }
+        Some(encodings)
+    }
+}
+
+enum Decoder {
+    Gzip(GzDecoder<Vec<u8>>),
+    /// `deflate` is meant to be zlib-wrapped, some servers send raw deflate data though. Bytes are
+    /// kept until the header tells which one it is.
+    DeflatePending(Vec<u8>),
+    Zlib(ZlibDecoder<Vec<u8>>),
+    RawDeflate(DeflateDecoder<Vec<u8>>),
+    Brotli(Box<brotli::DecompressorWriter<Vec<u8>>>),
+    Zstd(zstd::stream::write::Decoder<'static, Vec<u8>>),
+}
+
+impl Decoder {
+    fn new(encoding: Encoding) -> io::Result<Self> {
+        Ok(match encoding {
+            Encoding::Gzip => Self::Gzip(GzDecoder::new(vec![])),
+            Encoding::Deflate => Self::DeflatePending(vec![]),
+            Encoding::Brotli => Self::Brotli(Box::new(brotli::DecompressorWriter::new(
+                vec![],
+                BROTLI_BUFFER_SIZE,
+            ))),
+            Encoding::Zstd => Self::Zstd(zstd::stream::write::Decoder::new(vec![])?),
+        })
+    }
+
+    fn decode(&mut self, chunk: &[u8]) -> io::Result<Vec<u8>> {
+        if let Self::DeflatePending(pending) = self {
+            pending.extend_from_slice(chunk);
+            if pending.len() < 2 {
+                return Ok(vec![]);
+            }
+            let pending = mem::take(pending);
+            *self = if is_zlib_header(pending[0], pending[1]) {
+                Self::Zlib(ZlibDecoder::new(vec![]))
+            } else {
+                Self::RawDeflate(DeflateDecoder::new(vec![]))
+            };
+            return self.decode(&pending);
+        }
+
+        match self {
+            Self::Gzip(decoder) => write(decoder, chunk),
+            Self::Zlib(decoder) => write(decoder, chunk),
+            Self::RawDeflate(decoder) => write(decoder, chunk),
+            Self::Brotli(decoder) => write(decoder.as_mut(), chunk),
+            Self::Zstd(decoder) => write(decoder, chunk),
+            Self::DeflatePending(_) => unreachable!("pending deflate data is handled above"),
+        }?;
+        Ok(self.take_output())
+    }
+
+    /// Flush what remains once the whole body went through.
+    fn finish(&mut self) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Gzip(decoder) => decoder.try_finish(),
+            Self::Zlib(decoder) => decoder.try_finish(),
+            Self::RawDeflate(decoder) => decoder.try_finish(),
+            Self::Brotli(decoder) => decoder.close(),
+            Self::Zstd(decoder) => decoder.flush(),
+            Self::DeflatePending(pending) if pending.is_empty() => Ok(()),
+            Self::DeflatePending(_) => Err(io::ErrorKind::UnexpectedEof.into()),
+        }?;
+        Ok(self.take_output())
+    }
+
+    fn take_output(&mut self) -> Vec<u8> {
+        let output = match self {
+            Self::Gzip(decoder) => decoder.get_mut(),
+            Self::Zlib(decoder) => decoder.get_mut(),
+            Self::RawDeflate(decoder) => decoder.get_mut(),
+            Self::Brotli(decoder) => decoder.get_mut(),
+            Self::Zstd(decoder) => decoder.get_mut(),
+            Self::DeflatePending(_) => return vec![],
+        };
+        mem::take(output)
+    }
+}
+
+fn write<W: Write + ?Sized>(writer: &mut W, chunk: &[u8]) -> io::Result<()> {
+    writer.write_all(chunk)?;
+    writer.flush()
+}
+
+/// Whether the two bytes are a zlib header (RFC 1950): deflate method and valid check bits.
+fn is_zlib_header(cmf: u8, flg: u8) -> bool {
+    cmf & 0x0f == 8 && (u16::from(cmf) << 8 | u16::from(flg)) % 31 == 0
+}
+
+/// Decodes a body chunk by chunk, undoing its codings in reverse order.
+pub struct Decoding {
+    decoders: Vec<Decoder>,
+    received: bool,
+}
+
+impl Decoding {
+    pub fn new(encodings: &[Encoding]) -> Result<Self, SilqError> {
+        let decoders = encodings
+            .iter()
+            .rev()
+            .map(|encoding| Decoder::new(*encoding))
+            .collect::<io::Result<_>>()
+            .map_err(|err| SilqError::from("Unable to initialize decoder", &err))?;
+        Ok(Self {
+            decoders,
+            received: false,
+        })
+    }
+
+    pub fn decode(&mut self, chunk: &[u8]) -> Result<Vec<u8>, SilqError> {
+        self.received |= !chunk.is_empty();
+        let mut data = chunk.to_vec();
+        for decoder in &mut self.decoders {
+            data = decoder
+                .decode(&data)
+                .map_err(|err| SilqError::from("Unable to decode body", &err))?;
+        }
+        Ok(data)
+    }
+
+    /// Data remaining at the end of the body.
+    pub fn finish(&mut self) -> Result<Vec<u8>, SilqError> {
+        // Empty bodies, e.g. of HEAD requests, aren't encoded
+        if !self.received {
+            return Ok(vec![]);
+        }
+        let mut data = vec![];
+        for decoder in &mut self.decoders {
+            let mut decoded = decoder
+                .decode(&data)
+                .map_err(|err| SilqError::from("Unable to decode body", &err))?;
+            decoded.extend(
+                decoder
+                    .finish()
+                    .map_err(|err| SilqError::from("Unable to decode body", &err))?,
+            );
+            data = decoded;
+        }
+        Ok(data)
+    }
+}
+
+/// Coding and compression level applied to request bodies.
+#[derive(Clone, Copy)]
+pub struct Compression {
+    pub encoding: Encoding,
+    level: u32,
+}
+
+impl Compression {
+    /// Parse the codec's name, `level` defaults to a balance between speed and ratio.
+    pub fn parse(codec: &str, level: Option<i64>) -> Result<Self, SilqError> {
+        let encoding = Encoding::parse(codec).ok_or_else(|| {
+            SilqError::new(format!(
+                "Unsupported compression codec: {codec}, expected gzip, deflate, br or zstd"
+            ))
+        })?;
+        let (default, levels) = match encoding {
+            Encoding::Gzip | Encoding::Deflate => (6, 0..=9),
+            Encoding::Brotli => (5, 0..=11),
+            Encoding::Zstd => (3, 1..=22),
+        };
+        let level = level.unwrap_or(default);
+        if !levels.contains(&level) {
+            Err(SilqError::new(format!(
+                "Invalid {codec} compression level: {level}, expected {} to {}",
+                levels.start(),
+                levels.end()
+            )))?
+        }
+        Ok(Self {
+            encoding,
+            level: level as u32,
+        })
+    }
+
+    pub fn encoder(&self) -> Result<Encoder, SilqError> {
+        Ok(match self.encoding {
+            Encoding::Gzip => {
+                Encoder::Gzip(GzEncoder::new(vec![], flate2::Compression::new(self.level)))
+            }
+            Encoding::Deflate => Encoder::Deflate(ZlibEncoder::new(
+                vec![],
+                flate2::Compression::new(self.level),
+            )),
+            Encoding::Brotli => Encoder::Brotli(Some(Box::new(brotli::CompressorWriter::new(
+                vec![],
+                BROTLI_BUFFER_SIZE,
+                self.level,
+                BROTLI_WINDOW_BITS,
+            )))),
+            Encoding::Zstd => Encoder::Zstd(
+                zstd::stream::write::Encoder::new(vec![], self.level as i32)
+                    .map_err(|err| SilqError::from("Unable to initialize encoder", &err))?,
+            ),
+        })
+    }
+
+    pub fn compress(&self, data: &[u8]) -> Result<Vec<u8>, SilqError> {
+        let mut encoder = self.encoder()?;
+        let mut compressed = encoder.encode(data)?;
+        compressed.extend(encoder.finish()?);
+        Ok(compressed)
+    }
+}
+
+/// Compresses a body chunk by chunk.
+pub enum Encoder {
+    Gzip(GzEncoder<Vec<u8>>),
+    Deflate(ZlibEncoder<Vec<u8>>),
+    /// Brotli's writer only finishes the stream when consumed.
+    Brotli(Option<Box<brotli::CompressorWriter<Vec<u8>>>>),
+    Zstd(zstd::stream::write::Encoder<'static, Vec<u8>>),
+}
+
+impl Encoder {
+    /// Compress the chunk, returning the output produced so far.
+    pub fn encode(&mut self, chunk: &[u8]) -> Result<Vec<u8>, SilqError> {
+        let output = match self {
+            Self::Gzip(encoder) => encoder.write_all(chunk).map(|_| encoder.get_mut()),
+            Self::Deflate(encoder) => encoder.write_all(chunk).map(|_| encoder.get_mut()),
+            Self::Brotli(Some(encoder)) => encoder.write_all(chunk).map(|_| encoder.get_mut()),
+            Self::Brotli(None) => Err(io::Error::new(
+                io::ErrorKind::Other,
+                "compression already finished",
+            )),
+            Self::Zstd(encoder) => encoder.write_all(chunk).map(|_| encoder.get_mut()),
+        }
+        .map_err(|err| SilqError::from("Unable to compress body", &err))?;
+        Ok(mem::take(output))
+    }
+
+    /// End the stream, returning the remaining output.
+    pub fn finish(&mut self) -> Result<Vec<u8>, SilqError> {
+        let output = match self {
+            Self::Gzip(encoder) => encoder.try_finish().map(|_| mem::take(encoder.get_mut())),
+            Self::Deflate(encoder) => encoder.try_finish().map(|_| mem::take(encoder.get_mut())),
+            Self::Brotli(encoder) => Ok(encoder
+                .take()
+                .map(|encoder| encoder.into_inner())
+                .unwrap_or_default()),
+            Self::Zstd(encoder) => encoder.do_finish().map(|_| mem::take(encoder.get_mut())),
+        };
+        output.map_err(|err| SilqError::from("Unable to compress body", &err))
+    }
+}
@@ -1,25 +1,144 @@
 use std::error::Error;
 
-use ext_php_rs::exception::PhpException;
+use ext_php_rs::{
+    class::RegisteredClass,
+    exception::PhpException,
+    prelude::*,
+    types::{ZendObject, Zval},
+    zend::ce,
+};
+
+use crate::callable::Callable;
+
+/// Operations that can run out of time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timeout {
+    Connect,
+    TlsHandshake,
+    Response,
+    Read,
+    Deadline,
+}
+
+impl Timeout {
+    fn describe(&self) -> &'static str {
+        match self {
+            Timeout::Connect => "Connection timed out",
+            Timeout::TlsHandshake => "TLS handshake timed out",
+            Timeout::Response => "Response timed out",
+            Timeout::Read => "Body read timed out",
+            Timeout::Deadline => "Request deadline exceeded",
+        }
+    }
+
+    fn code(&self) -> i32 {
+        match self {
+            Timeout::Connect => TimeoutException::CONNECT,
+            Timeout::TlsHandshake => TimeoutException::TLS_HANDSHAKE,
+            Timeout::Response => TimeoutException::RESPONSE,
+            Timeout::Read => TimeoutException::READ,
+            Timeout::Deadline => TimeoutException::DEADLINE,
+        }
+    }
+}
+
+/// Connection failures, likely transient.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Failure {
+    /// The connection couldn't be established, host resolution included.
+    Connect,
+    /// The connection was closed before the response was received.
+    Reset,
+    /// The connection was closed before the request was written to it, e.g. a pooled connection
+    /// the server closed as it was reused. The request didn't reach the server.
+    Unsent,
+    /// A pooled connection was closed before the response was received, likely by the server
+    /// closing it as it was reused. The server may have received the request.
+    Stale,
+}
 
 pub struct SilqError {
     pub description: String,
+    pub timeout: Option<Timeout>,
+    pub failure: Option<Failure>,
 }
 
 impl SilqError {
     pub fn new(description: String) -> Self {
-        Self { description }
+        Self {
+            description,
+            timeout: None,
+            failure: None,
+        }
     }
 
     pub fn from<T: Error>(context: &str, error: &T) -> Self {
         Self {
             description: format!("{context}: {}", error),
+            timeout: None,
+            failure: None,
+        }
+    }
+
+    pub fn timeout(timeout: Timeout) -> Self {
+        Self {
+            description: timeout.describe().to_string(),
+            timeout: Some(timeout),
+            failure: None,
+        }
+    }
+
+    pub fn with_failure(self, failure: Failure) -> Self {
+        Self {
+            failure: Some(failure),
+            ..self
         }
     }
+
+    fn message(&self) -> String {
+        format!("Silq Exception: {}", self.description)
+    }
+
+    /// Exception object as it would be thrown, for callers collecting errors.
+    pub fn to_exception(&self) -> Result<Zval, SilqError> {
+        let (class, code) = match self.timeout {
+            Some(timeout) => (TimeoutException::get_metadata().ce(), timeout.code()),
+            None => (ce::exception(), 0),
+        };
+        let mut object = ZendObject::new(class);
+        let mut exception = Zval::new();
+        exception.set_object(&mut object);
+        Callable::method(&exception, "__construct")?
+            .call("Exception constructor", vec![&self.message(), &code])?;
+        Ok(exception)
+    }
 }
 
 impl From<SilqError> for PhpException {
     fn from(value: SilqError) -> PhpException {
-        PhpException::default(format!("Silq Exception: {}", value.description))
+        let message = value.message();
+        match value.timeout {
+            Some(timeout) => PhpException::new(
+                message,
+                timeout.code(),
+                TimeoutException::get_metadata().ce(),
+            ),
+            None => PhpException::default(message),
+        }
     }
 }
+
+/// Raised when an operation runs out of time. The exception's code tells which timeout expired.
+#[php_class(name = "Silq\\TimeoutException")]
+#[extends(ce::exception())]
+#[derive(Default)]
+pub struct TimeoutException;
+
+#[php_impl]
+impl TimeoutException {
+    pub const CONNECT: i32 = 1;
+    pub const TLS_HANDSHAKE: i32 = 2;
+    pub const RESPONSE: i32 = 3;
+    pub const READ: i32 = 4;
+    pub const DEADLINE: i32 = 5;
+}
@@ -0,0 +1,173 @@
+//! Integration with PHP event loops, e.g. Revolt: instead of blocking the process, operations
+//! waiting for the network suspend the current Fiber until a notification descriptor is readable.
+use std::cell::RefCell;
+use std::task::{Context, Poll};
+
+use ext_php_rs::{prelude::*, types::Zval};
+
+use crate::{callable::Callable, error::SilqError};
+
+thread_local! {
+    /// Called with a file descriptor to wait for, instead of blocking.
+    static SUSPENDER: RefCell<Option<Callable>> = RefCell::new(None);
+}
+
+/// Suspender set with `EventLoop::setSuspender()`, if any.
+pub fn suspender() -> Option<Callable> {
+    SUSPENDER.with(|suspender| suspender.borrow().clone())
+}
+
+/// Forget the suspender at the end of the PHP request, which it belongs to.
+pub fn reset() {
+    SUSPENDER.with(|suspender| suspender.borrow_mut().take());
+}
+
+#[cfg(unix)]
+mod notification {
+    use std::io::{Read, Write};
+    use std::os::unix::{io::AsRawFd, net::UnixStream};
+    use std::sync::{Arc, Mutex, PoisonError};
+    use std::task::Wake;
+
+    use crate::error::SilqError;
+
+    /// Write ends of the descriptors operations are waiting on.
+    static WAITERS: Mutex<Vec<Arc<UnixStream>>> = Mutex::new(Vec::new());
+
+    /// Notification descriptor of an operation waiting in the event loop, registered until
+    /// dropped.
+    pub struct Waiter {
+        reader: UnixStream,
+        writer: Arc<UnixStream>,
+    }
+
+    impl Waiter {
+        pub fn register() -> Result<Self, SilqError> {
+            let error = |err: std::io::Error| {
+                SilqError::from("Unable to create notification descriptor", &err)
+            };
+            let (reader, writer) = UnixStream::pair().map_err(error)?;
+            reader.set_nonblocking(true).map_err(error)?;
+            writer.set_nonblocking(true).map_err(error)?;
+            let writer = Arc::new(writer);
+            WAITERS
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(writer.clone());
+            Ok(Self { reader, writer })
+        }
+
+        pub fn fd(&self) -> i64 {
+            self.reader.as_raw_fd().into()
+        }
+
+        /// Consume pending notifications, before polling again.
+        pub fn drain(&self) {
+            let mut buf = [0; 64];
+            while matches!((&self.reader).read(&mut buf), Ok(read) if read > 0) {}
+        }
+    }
+
+    impl Drop for Waiter {
+        fn drop(&mut self) {
+            WAITERS
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .retain(|writer| !Arc::ptr_eq(writer, &self.writer));
+        }
+    }
+
+    /// Wakes every waiting operation, as requests in flight are polled by whichever waits next.
+    pub struct Notifier;
+
+    impl Wake for Notifier {
+        fn wake(self: Arc<Self>) {
+            for writer in WAITERS
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter()
+            {
+                // A full buffer already notifies the waiter
+                let _ = (&**writer).write(&[1]);
+            }
+        }
+    }
+}
+
+/// Poll until ready, calling `suspender` with a descriptor to wait for whenever pending.
+#[cfg(unix)]
+pub fn suspend_until<T>(
+    suspender: &Callable,
+    mut poll: impl FnMut(&mut Context) -> Poll<T>,
+) -> Result<T, SilqError> {
+    use std::sync::Arc;
+    use std::task::Waker;
+
+    use notification::{Notifier, Waiter};
+
+    let waiter = Waiter::register()?;
+    let waker = Waker::from(Arc::new(Notifier));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        waiter.drain();
+        // Other Fibers enter the runtime while this one is suspended
+        let polled = {
+            let _guard = crate::get_runtime().enter();
+            poll(&mut cx)
+        };
+        if let Poll::Ready(output) = polled {
+            return Ok(output);
+        }
+        suspender.call("Event loop suspender", vec![&waiter.fd()])?;
+    }
+}
+
+#[cfg(not(unix))]
+pub fn suspend_until<T>(
+    _suspender: &Callable,
+    _poll: impl FnMut(&mut Context) -> Poll<T>,
+) -> Result<T, SilqError> {
+    Err(SilqError::new(
+        "Event loop integration is not supported on this platform".to_string(),
+    ))
+}
+
+/// Integration with PHP event loops running Fibers, e.g. Revolt.
+///
+/// Once a suspender is set, sending requests, waiting for futures and reading bodies no longer
+/// block the process. Whenever they would, they call the suspender with a file descriptor that
+/// becomes readable when they may progress, and poll again once it returns. With Revolt:
+///
+/// ```php
+/// EventLoop::setSuspender(function (int $fd) {
+///     $stream = fopen("php://fd/$fd", 'r');
+///     $suspension = Revolt\EventLoop::getSuspension();
+///     $watcher = Revolt\EventLoop::onReadable($stream, fn () => $suspension->resume());
+///     $suspension->suspend();
+///     Revolt\EventLoop::cancel($watcher);
+///     fclose($stream);
+/// });
+/// ```
+#[php_class(name = "Silq\\EventLoop")]
+pub struct EventLoop {}
+
+#[php_impl]
+impl EventLoop {
+    /// Set the callable suspending the current Fiber until the given file descriptor is readable,
+    /// or null to block again.
+    ///
+    /// @param suspender callable(int $fd): void|null
+    pub fn set_suspender(suspender: &Zval) -> PhpResult<()> {
+        let suspender = match suspender.is_null() {
+            true => None,
+            false => Some(Callable::new(suspender, "Event loop suspender")?),
+        };
+        if cfg!(not(unix)) && suspender.is_some() {
+            Err(SilqError::new(
+                "Event loop integration is not supported on this platform".to_string(),
+            ))?
+        }
+        SUSPENDER.with(|current| *current.borrow_mut() = suspender);
+        Ok(())
+    }
+}
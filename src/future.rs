@@ -0,0 +1,286 @@
+//! Requests sent in the background with `RequestBuilder::sendAsync()`.
+//!
+//! Their futures can't leave PHP's thread, where their callbacks have to run. Connections are
+//! opened and requests sent by tasks on the runtime, while the futures themselves, e.g. following
+//! redirects or feeding streamed bodies, are polled whenever the extension blocks, e.g. in
+//! `Future::wait()` or another request's `send()`.
+use std::cell::RefCell;
+use std::future::{poll_fn, Future};
+use std::pin::{pin, Pin};
+use std::rc::{Rc, Weak};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+use ext_php_rs::{
+    boxed::ZBox,
+    convert::IntoZval,
+    prelude::*,
+    types::{ZendClassObject, ZendHashTable},
+};
+
+use crate::{error::SilqError, event_loop, get_runtime, Response};
+
+pub type Exchange = Pin<Box<dyn Future<Output = Result<Response, SilqError>>>>;
+
+thread_local! {
+    /// Requests still running, polled whenever the extension blocks.
+    static IN_FLIGHT: RefCell<Vec<Weak<RefCell<Task>>>> = RefCell::new(vec![]);
+}
+
+enum Task {
+    Running(Exchange),
+    Done(Result<Response, SilqError>),
+    Taken,
+}
+
+impl Task {
+    fn poll(&mut self, cx: &mut Context) {
+        if let Task::Running(exchange) = self {
+            if let Poll::Ready(result) = exchange.as_mut().poll(cx) {
+                *self = Task::Done(result);
+            }
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        matches!(self, Task::Running(_))
+    }
+
+    /// Take the outcome of the completed request, or None if it is still running.
+    fn take(&mut self) -> Option<Result<Response, SilqError>> {
+        match std::mem::replace(self, Task::Taken) {
+            Task::Running(exchange) => {
+                *self = Task::Running(exchange);
+                None
+            }
+            Task::Done(result) => Some(result),
+            Task::Taken => Some(Err(SilqError::new(
+                "Response already taken from this future".to_string(),
+            ))),
+        }
+    }
+}
+
+/// Wakes nothing, for polls made outside of the runtime, which polls again when blocking.
+struct NoopWaker;
+
+impl Wake for NoopWaker {
+    fn wake(self: Arc<Self>) {}
+}
+
+/// Poll every request in flight once.
+fn poll_in_flight(cx: &mut Context) {
+    // Callbacks run while polling may start new requests
+    let tasks = IN_FLIGHT.with(|tasks| {
+        let mut tasks = tasks.borrow_mut();
+        tasks.retain(|task| task.strong_count() > 0);
+        tasks.iter().filter_map(Weak::upgrade).collect::<Vec<_>>()
+    });
+    for task in tasks {
+        // A task can't be polled from its own callbacks
+        if let Ok(mut task) = task.try_borrow_mut() {
+            task.poll(cx);
+        }
+    }
+    IN_FLIGHT.with(|tasks| {
+        tasks.borrow_mut().retain(|task| {
+            task.upgrade()
+                .is_some_and(|task| task.try_borrow().map_or(true, |task| task.is_running()))
+        })
+    });
+}
+
+/// Run `future` to completion on the runtime, carrying on the requests in flight meanwhile.
+/// Suspends the current Fiber instead of blocking if an event loop suspender is set.
+pub fn block_on<F: Future>(future: F) -> Result<F::Output, SilqError> {
+    let mut future = pin!(future);
+    let poll = |cx: &mut Context| {
+        poll_in_flight(cx);
+        future.as_mut().poll(cx)
+    };
+    match event_loop::suspender() {
+        Some(suspender) => event_loop::suspend_until(&suspender, poll),
+        None => Ok(get_runtime().block_on(poll_fn(poll))),
+    }
+}
+
+/// Response of a request sent in the background with `RequestBuilder::sendAsync()`.
+///
+/// Dropping the future before the response is received cancels the request.
+#[php_class(name = "Silq\\Future")]
+pub struct ResponseFuture {
+    task: Rc<RefCell<Task>>,
+}
+
+impl ResponseFuture {
+    /// Start sending the request, up to the point it waits for the runtime's tasks.
+    pub fn spawn(exchange: impl Future<Output = Result<Response, SilqError>> + 'static) -> Self {
+        let task = Rc::new(RefCell::new(Task::Running(Box::pin(exchange))));
+        IN_FLIGHT.with(|tasks| tasks.borrow_mut().push(Rc::downgrade(&task)));
+        let future = Self { task };
+        future.poll();
+        future
+    }
+
+    /// Poll the request once, without blocking.
+    fn poll(&self) {
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let _guard = get_runtime().enter();
+        if let Ok(mut task) = self.task.try_borrow_mut() {
+            task.poll(&mut Context::from_waker(&waker));
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.task
+            .try_borrow()
+            .map_or(true, |task| task.is_running())
+    }
+
+    fn is_failed(&self) -> bool {
+        self.task
+            .try_borrow()
+            .is_ok_and(|task| matches!(&*task, Task::Done(Err(_)) | Task::Taken))
+    }
+
+    fn take(&self) -> Result<Response, SilqError> {
+        self.task
+            .try_borrow_mut()
+            .ok()
+            .and_then(|mut task| task.take())
+            .unwrap_or_else(|| Err(SilqError::new("Future is still running".to_string())))
+    }
+}
+
+#[php_impl]
+impl ResponseFuture {
+    /// Wait for the response.
+    ///
+    /// @return Response
+    pub fn wait(&self) -> PhpResult<Response> {
+        block_on(poll_fn(|_| match self.is_running() {
+            true => Poll::Pending,
+            false => Poll::Ready(()),
+        }))?;
+        Ok(self.take()?)
+    }
+
+    /// Returns whether the response, or the request's failure, is available without waiting.
+    pub fn is_ready(&self) -> bool {
+        self.poll();
+        !self.is_running()
+    }
+
+    /// Cancel the request, making `wait()` throw. Returns false if it had already completed.
+    pub fn cancel(&self) -> bool {
+        let mut task = match self.task.try_borrow_mut() {
+            Ok(task) => task,
+            Err(_) => return false,
+        };
+        if !task.is_running() {
+            return false;
+        }
+        *task = Task::Done(Err(SilqError::new("Request cancelled".to_string())));
+        true
+    }
+
+    /// Wait for all the responses, keeping the futures' keys. Throws the first failure.
+    ///
+    /// @param futures array<Future>
+    /// @return array<Response>
+    pub fn all(futures: &ZendHashTable) -> PhpResult<ZBox<ZendHashTable>> {
+        let tasks = futures_of(futures)?;
+        let failed = block_on(poll_fn(|_| {
+            if let Some((_, _, failed)) = tasks.iter().find(|(_, _, future)| future.is_failed()) {
+                return Poll::Ready(Some(*failed));
+            }
+            match tasks.iter().all(|(_, _, future)| !future.is_running()) {
+                true => Poll::Ready(None),
+                false => Poll::Pending,
+            }
+        }))?;
+        if let Some(failed) = failed {
+            failed.take()?;
+        }
+        let mut responses = ZendHashTable::new();
+        for (index, key, future) in tasks {
+            let response = future
+                .take()?
+                .into_zval(false)
+                .map_err(|err| SilqError::from("Unable to return response", &err))?;
+            match key {
+                Some(key) => responses.insert(&key, response),
+                None => responses.insert_at_index(index, response),
+            }
+            .map_err(|err| SilqError::from("Unable to return response", &err))?;
+        }
+        Ok(responses)
+    }
+
+    /// Wait for the first successful response. Throws the last future's failure if all requests
+    /// fail.
+    ///
+    /// @param futures array<Future>
+    /// @return Response
+    pub fn any(futures: &ZendHashTable) -> PhpResult<Response> {
+        let tasks = futures_of(futures)?;
+        if tasks.is_empty() {
+            Err(SilqError::new("No future to wait for".to_string()))?
+        }
+        let mut last = None;
+        let winner = block_on(poll_fn(|_| {
+            for (index, (_, _, future)) in tasks.iter().enumerate() {
+                let succeeded = future
+                    .task
+                    .try_borrow()
+                    .is_ok_and(|task| matches!(&*task, Task::Done(Ok(_))));
+                if succeeded {
+                    return Poll::Ready(Some(index));
+                }
+                if !future.is_running() {
+                    last = Some(index);
+                }
+            }
+            match tasks.iter().all(|(_, _, future)| !future.is_running()) {
+                true => Poll::Ready(None),
+                false => Poll::Pending,
+            }
+        }))?;
+        let index = winner.or(last).unwrap_or_default();
+        Ok(tasks[index].2.take()?)
+    }
+
+    /// Wait for the first request to complete, and return its response or throw its failure.
+    ///
+    /// @param futures array<Future>
+    /// @return Response
+    pub fn race(futures: &ZendHashTable) -> PhpResult<Response> {
+        let tasks = futures_of(futures)?;
+        if tasks.is_empty() {
+            Err(SilqError::new("No future to wait for".to_string()))?
+        }
+        let index = block_on(poll_fn(|_| {
+            match tasks.iter().position(|(_, _, future)| !future.is_running()) {
+                Some(index) => Poll::Ready(index),
+                None => Poll::Pending,
+            }
+        }))?;
+        Ok(tasks[index].2.take()?)
+    }
+}
+
+/// Futures of the given array, with their keys.
+fn futures_of(
+    futures: &ZendHashTable,
+) -> Result<Vec<(u64, Option<String>, &ResponseFuture)>, SilqError> {
+    futures
+        .iter()
+        .map(|(index, key, value)| {
+            value
+                .object()
+                .and_then(ZendClassObject::<ResponseFuture>::from_zend_obj)
+                .map(|future| (index, key, &**future))
+                .ok_or_else(|| SilqError::new("Futures must be Future objects".to_string()))
+        })
+        .collect()
+}
@@ -0,0 +1,71 @@
+//! Connection establishment racing the resolved addresses (RFC 8305), so that a broken address
+//! family doesn't stall requests until the OS gives up on it.
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use tokio::{net::TcpStream, task::JoinSet};
+
+/// Delay recommended by RFC 8305 between two connection attempts.
+pub const DEFAULT_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Connect to the first address answering. A new attempt starts every `attempt_delay`, or as soon
+/// as the previous one fails, alternating between IPv6 and IPv4 addresses.
+pub async fn connect(addresses: &[SocketAddr], attempt_delay: Duration) -> io::Result<TcpStream> {
+    let mut pending = interleave(addresses).into_iter().peekable();
+    let mut attempts = JoinSet::new();
+    let mut last_error = None;
+    let mut start_next = true;
+
+    loop {
+        if start_next {
+            if let Some(address) = pending.next() {
+                attempts.spawn(TcpStream::connect(address));
+            }
+            start_next = false;
+        }
+
+        if attempts.is_empty() {
+            return Err(last_error.unwrap_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "no address to connect to")
+            }));
+        }
+
+        // Dropping the set on return aborts the attempts still running
+        tokio::select! {
+            Some(result) = attempts.join_next() => match result {
+                Ok(Ok(stream)) => return Ok(stream),
+                Ok(Err(err)) => {
+                    last_error = Some(err);
+                    start_next = true;
+                }
+                Err(err) => {
+                    last_error = Some(io::Error::new(io::ErrorKind::Other, err));
+                    start_next = true;
+                }
+            },
+            _ = tokio::time::sleep(attempt_delay), if pending.peek().is_some() => {
+                start_next = true;
+            }
+        }
+    }
+}
+
+/// Order addresses by alternating families, starting with the family of the first address.
+fn interleave(addresses: &[SocketAddr]) -> Vec<SocketAddr> {
+    let Some(first) = addresses.first() else {
+        return vec![];
+    };
+    let (mut preferred, mut other): (Vec<_>, Vec<_>) = addresses
+        .iter()
+        .partition(|address| address.is_ipv6() == first.is_ipv6());
+    preferred.reverse();
+    other.reverse();
+
+    let mut ordered = Vec::with_capacity(addresses.len());
+    while !preferred.is_empty() || !other.is_empty() {
+        ordered.extend(preferred.pop());
+        ordered.extend(other.pop());
+    }
+    ordered
+}
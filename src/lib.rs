@@ -3,42 +3,95 @@
 #![warn(clippy::unwrap_used)]
 #![allow(clippy::should_implement_trait)]
 
+mod body;
+mod callable;
+mod concurrent;
+mod connection;
+mod cookie;
+mod digest;
+mod dns;
+mod encoding;
 mod error;
+mod event_loop;
+mod future;
+mod happy_eyeballs;
+mod multipart;
+mod oauth;
+mod payload;
+mod pool;
+mod proxy;
+mod redirect;
+mod retry;
 mod serde;
+mod sigv4;
+mod socks;
+mod timeout;
+mod tls;
 
+use std::borrow::Cow;
+use std::future::Future;
 use std::io::{BufReader, Cursor};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::{collections::HashMap, mem};
 
 use base64::{engine::general_purpose::STANDARD, Engine};
-use ext_php_rs::types::ZendHashTable;
 use ext_php_rs::{
     binary::Binary,
+    boxed::ZBox,
+    convert::IntoZval,
     prelude::*,
-    types::{ZendClassObject, Zval},
+    types::{ZendClassObject, ZendHashTable, ZendObject, Zval},
     zend::ce,
 };
-use http::{request::Builder, uri::Scheme, HeaderMap, HeaderValue, Method};
-use http_body_util::{BodyExt, Full};
+use http::{
+    request::Builder, uri::Scheme, HeaderMap, HeaderValue, Method, StatusCode, Uri, Version,
+};
 use hyper::{
-    body::{Bytes, Incoming},
-    header::{HeaderName, AUTHORIZATION, CONTENT_TYPE},
+    body::Incoming,
+    header::{
+        HeaderName, ACCEPT_ENCODING, AUTHORIZATION, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE,
+        HOST, PROXY_AUTHORIZATION,
+    },
     http::response::Parts,
 };
-use hyper_util::rt::TokioIo;
 use once_cell::sync::OnceCell;
 use rustls_pemfile::{read_one, Item};
-use tokio::{net::TcpStream, runtime::Runtime};
+#[cfg(unix)]
+use tokio::net::UnixStream;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    runtime::Runtime,
+    sync::Semaphore,
+    task::AbortHandle,
+};
 use tokio_rustls::rustls::{Certificate, PrivateKey};
 use tokio_rustls::{
-    rustls::{ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName},
+    rustls::{ClientConfig, ServerName},
     TlsConnector,
 };
-use webpki_roots::TLS_SERVER_ROOTS;
 
 use crate::{
-    error::SilqError,
+    body::{ResponseBody, SaveOptions},
+    connection::{handshake, HttpVersionPolicy, Sender},
+    cookie::{CookieJar, SetCookie},
+    digest::{DigestAuth, DigestSessions},
+    dns::{parse_ip, Lookup, Resolver},
+    encoding::{Compression, Decoding, Encoding},
+    error::{Failure, SilqError, Timeout},
+    future::ResponseFuture,
+    happy_eyeballs::DEFAULT_ATTEMPT_DELAY,
+    multipart::Multipart,
+    oauth::OAuth2,
+    payload::{feeding, Payload, RequestBody, Source},
+    pool::{Pool, PoolKey},
+    proxy::{NoProxy, Proxies, ProxyServer},
+    redirect::RedirectPolicy,
+    retry::RetryPolicy,
     serde::{ZvalDeserializer, ZvalSerializer},
+    sigv4::AwsSigner,
+    timeout::{within, Timeouts},
 };
 
 static RUNTIME: OnceCell<Runtime> = OnceCell::new();
@@ -47,16 +100,45 @@ static CONTENT_TYPE_JSON: HeaderValue = HeaderValue::from_static("application/js
 static CONTENT_TYPE_FORM: HeaderValue =
     HeaderValue::from_static("application/x-www-form-urlencoded");
 
+const DEFAULT_POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
 fn get_runtime() -> &'static Runtime {
     RUNTIME.get().expect("Uninitialized Silq Runtime")
 }
 
+fn parse_duration(seconds: f64) -> Result<Duration, SilqError> {
+    Duration::try_from_secs_f64(seconds).map_err(|err| SilqError::from("Invalid duration", &err))
+}
+
+fn parse_timeout(seconds: Option<f64>) -> Result<Option<Duration>, SilqError> {
+    seconds.map(parse_duration).transpose()
+}
+
 /// HTTP client builder
 #[php_class(name = "Silq\\HttpClientBuilder")]
 pub struct HttpClientBuilder {
     allow_unsecure_http: bool,
     client_identity: Option<ClientIdentity>,
     ca_cert: Option<CertificateAuthority>,
+    pool_idle_timeout: Option<Duration>,
+    pool_max_idle_per_host: usize,
+    http_version_policy: HttpVersionPolicy,
+    http2_prior_knowledge: bool,
+    timeouts: Timeouts,
+    proxy: Option<ProxyServer>,
+    proxy_credentials: Option<(String, String)>,
+    no_proxy: Option<NoProxy>,
+    proxy_from_env: bool,
+    unix_socket: Option<PathBuf>,
+    resolver: Resolver,
+    connect_attempt_delay: Duration,
+    redirect_policy: RedirectPolicy,
+    retry_policy: RetryPolicy,
+    decompression: bool,
+    cookie_jar: Option<CookieJar>,
+    oauth: Option<OAuth2>,
+    oauth_origins: Option<Vec<Uri>>,
+    aws_signer: Option<AwsSigner>,
 }
 
 #[php_impl]
@@ -67,6 +149,25 @@ impl HttpClientBuilder {
             allow_unsecure_http: false,
             client_identity: None,
             ca_cert: None,
+            pool_idle_timeout: Some(DEFAULT_POOL_IDLE_TIMEOUT),
+            pool_max_idle_per_host: usize::MAX,
+            http_version_policy: HttpVersionPolicy::Http1Only,
+            http2_prior_knowledge: false,
+            timeouts: Timeouts::default(),
+            proxy: None,
+            proxy_credentials: None,
+            no_proxy: None,
+            proxy_from_env: false,
+            unix_socket: None,
+            resolver: Resolver::default(),
+            connect_attempt_delay: DEFAULT_ATTEMPT_DELAY,
+            redirect_policy: RedirectPolicy::None,
+            retry_policy: RetryPolicy::none(),
+            decompression: true,
+            cookie_jar: None,
+            oauth: None,
+            oauth_origins: None,
+            aws_signer: None,
         }
     }
 
@@ -94,12 +195,399 @@ impl HttpClientBuilder {
         Ok(this)
     }
 
+    /// Set how long an unused connection is kept open for reuse.
+    ///
+    /// @param seconds float|null [default: 90] Idle timeout, null keeps connections indefinitely.
+    /// @return HttpClientBuilder
+    pub fn with_pool_idle_timeout(
+        #[this] this: &mut ZendClassObject<Self>,
+        seconds: Option<f64>,
+    ) -> PhpResult<&mut ZendClassObject<Self>> {
+        this.pool_idle_timeout = parse_timeout(seconds)?;
+        Ok(this)
+    }
+
+    /// Set the maximum number of idle connections kept open per host. Zero disables pooling.
+    ///
+    /// @param max int
+    /// @return HttpClientBuilder
+    pub fn with_pool_max_idle_per_host(
+        #[this] this: &mut ZendClassObject<Self>,
+        max: usize,
+    ) -> &mut ZendClassObject<Self> {
+        this.pool_max_idle_per_host = max;
+        this
+    }
+
+    /// Set which HTTP versions can be negotiated with servers through TLS' ALPN extension.
+    ///
+    /// @param policy string One of `http1` (default), `http2-preferred` or `http2`.
+    /// @return HttpClientBuilder
+    pub fn with_http_version_policy<'a>(
+        #[this] this: &'a mut ZendClassObject<Self>,
+        policy: &str,
+    ) -> PhpResult<&'a mut ZendClassObject<Self>> {
+        this.http_version_policy = HttpVersionPolicy::parse(policy)?;
+        Ok(this)
+    }
+
+    /// Speak HTTP/2 right away over unsecure HTTP connections (h2c), without upgrade nor
+    /// negotiation. Requires unsecure HTTP to be allowed.
+    ///
+    /// @param enable bool
+    /// @return HttpClientBuilder
+    pub fn with_http2_prior_knowledge(
+        #[this] this: &mut ZendClassObject<Self>,
+        enable: bool,
+    ) -> &mut ZendClassObject<Self> {
+        this.http2_prior_knowledge = enable;
+        this
+    }
+
+    /// Set the default time allowed to establish TCP connections.
+    ///
+    /// @param seconds float|null Timeout, null waits indefinitely.
+    /// @return HttpClientBuilder
+    pub fn with_connect_timeout(
+        #[this] this: &mut ZendClassObject<Self>,
+        seconds: Option<f64>,
+    ) -> PhpResult<&mut ZendClassObject<Self>> {
+        this.timeouts.connect = parse_timeout(seconds)?;
+        Ok(this)
+    }
+
+    /// Set the default time allowed to complete TLS handshakes.
+    ///
+    /// @param seconds float|null Timeout, null waits indefinitely.
+    /// @return HttpClientBuilder
+    pub fn with_tls_handshake_timeout(
+        #[this] this: &mut ZendClassObject<Self>,
+        seconds: Option<f64>,
+    ) -> PhpResult<&mut ZendClassObject<Self>> {
+        this.timeouts.tls_handshake = parse_timeout(seconds)?;
+        Ok(this)
+    }
+
+    /// Set the default time allowed between sending a request and receiving the response's head.
+    ///
+    /// @param seconds float|null Timeout, null waits indefinitely.
+    /// @return HttpClientBuilder
+    pub fn with_response_timeout(
+        #[this] this: &mut ZendClassObject<Self>,
+        seconds: Option<f64>,
+    ) -> PhpResult<&mut ZendClassObject<Self>> {
+        this.timeouts.response = parse_timeout(seconds)?;
+        Ok(this)
+    }
+
+    /// Set the default idle time allowed between two frames of a response's body.
+    ///
+    /// @param seconds float|null Timeout, null waits indefinitely.
+    /// @return HttpClientBuilder
+    pub fn with_read_timeout(
+        #[this] this: &mut ZendClassObject<Self>,
+        seconds: Option<f64>,
+    ) -> PhpResult<&mut ZendClassObject<Self>> {
+        this.timeouts.read = parse_timeout(seconds)?;
+        Ok(this)
+    }
+
+    /// Set the default time allowed for a whole exchange, from sending the request to reading the
+    /// end of the response's body.
+    ///
+    /// @param seconds float|null Timeout, null waits indefinitely.
+    /// @return HttpClientBuilder
+    pub fn with_timeout(
+        #[this] this: &mut ZendClassObject<Self>,
+        seconds: Option<f64>,
+    ) -> PhpResult<&mut ZendClassObject<Self>> {
+        this.timeouts.total = parse_timeout(seconds)?;
+        Ok(this)
+    }
+
+    /// Send all requests through the given proxy, e.g. `http://proxy.local:3128`. HTTPS requests
+    /// are tunneled with the CONNECT method. SOCKS5 proxies are supported with `socks5://` and
+    /// `socks5h://` URLs, the latter letting the proxy resolve host names.
+    ///
+    /// @param url string
+    /// @return HttpClientBuilder
+    pub fn with_proxy<'a>(
+        #[this] this: &'a mut ZendClassObject<Self>,
+        url: &str,
+    ) -> PhpResult<&'a mut ZendClassObject<Self>> {
+        this.proxy = Some(ProxyServer::parse(url)?);
+        Ok(this)
+    }
+
+    /// Authenticate to the proxy with basic authentication, or username/password for SOCKS5.
+    ///
+    /// @param user string user's name
+    /// @param password string password
+    /// @return HttpClientBuilder
+    pub fn with_proxy_credentials<'a>(
+        #[this] this: &'a mut ZendClassObject<Self>,
+        user: &str,
+        password: &str,
+    ) -> &'a mut ZendClassObject<Self> {
+        this.proxy_credentials = Some((user.to_string(), password.to_string()));
+        this
+    }
+
+    /// Set the hosts reached without proxy. Entries match the host and its subdomains, `*`
+    /// matches all hosts.
+    ///
+    /// @param hosts string[]
+    /// @return HttpClientBuilder
+    pub fn with_no_proxy(
+        #[this] this: &mut ZendClassObject<Self>,
+        hosts: Vec<String>,
+    ) -> &mut ZendClassObject<Self> {
+        this.no_proxy = Some(NoProxy::from_entries(hosts.iter().map(String::as_str)));
+        this
+    }
+
+    /// Read proxies from the `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY` environment variables.
+    /// Proxies configured explicitly take precedence.
+    ///
+    /// @param enable bool
+    /// @return HttpClientBuilder
+    pub fn with_proxy_from_env(
+        #[this] this: &mut ZendClassObject<Self>,
+        enable: bool,
+    ) -> &mut ZendClassObject<Self> {
+        this.proxy_from_env = enable;
+        this
+    }
+
+    /// Connect to the Unix domain socket at the given path instead of the URIs' host, which
+    /// is still used for the `Host` header and TLS. Proxies are ignored.
+    ///
+    /// @param path string
+    /// @return HttpClientBuilder
+    pub fn with_unix_socket<'a>(
+        #[this] this: &'a mut ZendClassObject<Self>,
+        path: &str,
+    ) -> &'a mut ZendClassObject<Self> {
+        this.unix_socket = Some(PathBuf::from(path));
+        this
+    }
+
+    /// Resolve the host name to the given IP addresses instead of querying DNS, like curl's
+    /// `--resolve`. TLS still verifies the server's certificate against the host name.
+    ///
+    /// @param host string
+    /// @param addresses string[] IPv4 or IPv6 addresses, tried in order.
+    /// @return HttpClientBuilder
+    pub fn with_dns_override<'a>(
+        #[this] this: &'a mut ZendClassObject<Self>,
+        host: &str,
+        addresses: Vec<String>,
+    ) -> PhpResult<&'a mut ZendClassObject<Self>> {
+        let addresses = addresses
+            .iter()
+            .map(|address| parse_ip(address))
+            .collect::<Result<Vec<_>, _>>()?;
+        if addresses.is_empty() {
+            Err(SilqError::new(format!("No address given for {host}")))?
+        }
+        this.resolver.with_override(host, addresses);
+        Ok(this)
+    }
+
+    /// Resolve host names with the given callable, called with the host name and returning an IP
+    /// address, a list of them, or null to fall back to the system resolver.
+    ///
+    /// @param resolver callable(string): string|string[]|null
+    /// @return HttpClientBuilder
+    pub fn with_dns_resolver<'a>(
+        #[this] this: &'a mut ZendClassObject<Self>,
+        resolver: &Zval,
+    ) -> PhpResult<&'a mut ZendClassObject<Self>> {
+        this.resolver.with_callback(resolver)?;
+        Ok(this)
+    }
+
+    /// Cache resolved addresses for the given time. Clones of the client share the cache.
+    ///
+    /// @param seconds float|null [default: null] Time to live, null disables the cache.
+    /// @return HttpClientBuilder
+    pub fn with_dns_cache_ttl(
+        #[this] this: &mut ZendClassObject<Self>,
+        seconds: Option<f64>,
+    ) -> PhpResult<&mut ZendClassObject<Self>> {
+        this.resolver.with_cache_ttl(parse_timeout(seconds)?);
+        Ok(this)
+    }
+
+    /// Set the delay before trying the next address of a host while previous connection attempts
+    /// are still pending, alternating between IPv6 and IPv4 (Happy Eyeballs).
+    ///
+    /// @param seconds float [default: 0.25]
+    /// @return HttpClientBuilder
+    pub fn with_connect_attempt_delay(
+        #[this] this: &mut ZendClassObject<Self>,
+        seconds: f64,
+    ) -> PhpResult<&mut ZendClassObject<Self>> {
+        this.connect_attempt_delay = parse_duration(seconds)?;
+        Ok(this)
+    }
+
+    /// Set which redirects to follow: none with 0 (default), up to the given number, failing
+    /// past it, or those the callable returns true for. 301 and 302 redirects turn POST requests
+    /// into GET ones, 303 redirects turn all but HEAD requests into GET ones. Authorization
+    /// and cookie headers are dropped when leaving the original origin.
+    ///
+    /// @param policy int|callable(string $uri, int $status, string[] $redirects): bool
+    /// @return HttpClientBuilder
+    pub fn with_redirect_policy<'a>(
+        #[this] this: &'a mut ZendClassObject<Self>,
+        policy: &Zval,
+    ) -> PhpResult<&'a mut ZendClassObject<Self>> {
+        this.redirect_policy = RedirectPolicy::parse(policy)?;
+        Ok(this)
+    }
+
+    /// Retry failed attempts according to the given policy. Requests are not retried by default.
+    ///
+    /// @param policy RetryPolicy
+    /// @return HttpClientBuilder
+    pub fn with_retry_policy<'a>(
+        #[this] this: &'a mut ZendClassObject<Self>,
+        policy: &ZendClassObject<RetryPolicy>,
+    ) -> &'a mut ZendClassObject<Self> {
+        this.retry_policy = (*policy).clone();
+        this
+    }
+
+    /// Advertise gzip, deflate, brotli and zstd support through the `Accept-Encoding` header, and
+    /// decode response bodies accordingly.
+    ///
+    /// @param enable bool [default: true]
+    /// @return HttpClientBuilder
+    pub fn with_decompression(
+        #[this] this: &mut ZendClassObject<Self>,
+        enable: bool,
+    ) -> &mut ZendClassObject<Self> {
+        this.decompression = enable;
+        this
+    }
+
+    /// Store the cookies set by responses in the given jar, and send them back with the matching
+    /// requests. Jars can be shared by several clients.
+    ///
+    /// @param jar CookieJar
+    /// @return HttpClientBuilder
+    pub fn with_cookie_jar<'a>(
+        #[this] this: &'a mut ZendClassObject<Self>,
+        jar: &ZendClassObject<CookieJar>,
+    ) -> &'a mut ZendClassObject<Self> {
+        this.cookie_jar = Some((*jar).clone());
+        this
+    }
+
+    /// Authenticate requests with OAuth 2 access tokens obtained from the token endpoint with the
+    /// client credentials grant. Tokens are cached until shortly before they expire, shared by the
+    /// clients built and their clones, and fetched again once when a server answers `401`. They
+    /// are only sent to the token endpoint's origin, unless set by `withOAuth2ScopeOrigins()`,
+    /// and not along requests with their own `Authorization` header.
+    ///
+    /// @param token_uri string
+    /// @param client_id string
+    /// @param client_secret string
+    /// @param scopes string[]|null
+    /// @return HttpClientBuilder
+    pub fn with_oauth2_client_credentials<'a>(
+        #[this] this: &'a mut ZendClassObject<Self>,
+        token_uri: &str,
+        client_id: &str,
+        client_secret: &str,
+        scopes: Option<Vec<String>>,
+    ) -> PhpResult<&'a mut ZendClassObject<Self>> {
+        this.oauth = Some(OAuth2::client_credentials(
+            token_uri,
+            client_id,
+            client_secret,
+            scopes,
+        )?);
+        Ok(this)
+    }
+
+    /// Authenticate requests with OAuth 2 access tokens obtained from the token endpoint with the
+    /// given refresh token, replaced by the ones the endpoint issues. See
+    /// `withOAuth2ClientCredentials()`. Public clients have no secret.
+    ///
+    /// @param token_uri string
+    /// @param refresh_token string
+    /// @param client_id string
+    /// @param client_secret string|null
+    /// @param scopes string[]|null
+    /// @return HttpClientBuilder
+    pub fn with_oauth2_refresh_token<'a>(
+        #[this] this: &'a mut ZendClassObject<Self>,
+        token_uri: &str,
+        refresh_token: &str,
+        client_id: &str,
+        client_secret: Option<String>,
+        scopes: Option<Vec<String>>,
+    ) -> PhpResult<&'a mut ZendClassObject<Self>> {
+        this.oauth = Some(OAuth2::refresh_token(
+            token_uri,
+            refresh_token,
+            client_id,
+            client_secret.as_deref(),
+            scopes,
+        )?);
+        Ok(this)
+    }
+
+    /// Send OAuth 2 access tokens to the origins of the given URIs, e.g. `https://api.example.com`,
+    /// rather than to the token endpoint's origin.
+    ///
+    /// @param origins string[]
+    /// @return HttpClientBuilder
+    pub fn with_oauth2_scope_origins(
+        #[this] this: &mut ZendClassObject<Self>,
+        origins: Vec<String>,
+    ) -> PhpResult<&mut ZendClassObject<Self>> {
+        let origins = origins
+            .iter()
+            .map(|origin| match origin.parse::<Uri>() {
+                Ok(uri) if uri.scheme().is_some() && uri.authority().is_some() => Ok(uri),
+                _ => Err(SilqError::new(format!(
+                    "Invalid OAuth 2 scope origin '{origin}', expected e.g. https://api.example.com"
+                ))),
+            })
+            .collect::<Result<_, _>>()?;
+        this.oauth_origins = Some(origins);
+        Ok(this)
+    }
+
+    /// Sign requests with AWS Signature Version 4, e.g. to call AWS services or S3-compatible
+    /// storages. Requests can override it with `RequestBuilder::withAwsSigner()`.
+    ///
+    /// @param signer AwsSigner
+    /// @return HttpClientBuilder
+    pub fn with_aws_signer<'a>(
+        #[this] this: &'a mut ZendClassObject<Self>,
+        signer: &ZendClassObject<AwsSigner>,
+    ) -> &'a mut ZendClassObject<Self> {
+        this.aws_signer = Some((*signer).clone());
+        this
+    }
+
     pub fn build(&mut self) -> PhpResult<HttpClient> {
+        if self.http2_prior_knowledge && !self.allow_unsecure_http {
+            Err(SilqError::new(
+                "HTTP/2 prior knowledge requires unsecure HTTP to be allowed".to_string(),
+            ))?
+        }
+
         let transport_security = match self {
             HttpClientBuilder {
                 allow_unsecure_http: true,
                 client_identity: None,
                 ca_cert: None,
+                ..
             } => TransportSecurity::AllowUnsecure,
             HttpClientBuilder {
                 allow_unsecure_http: false,
@@ -115,7 +603,51 @@ impl HttpClientBuilder {
             ))?,
         };
 
-        Ok(HttpClient { transport_security })
+        let tls_config = tls::client_config(&transport_security, self.http_version_policy)?;
+
+        let mut proxies = if self.proxy_from_env {
+            Proxies::from_env()?
+        } else {
+            Proxies::default()
+        };
+        if let Some(proxy) = &self.proxy {
+            proxies.http = Some(proxy.clone());
+            proxies.https = Some(proxy.clone());
+        }
+        if let Some(no_proxy) = &self.no_proxy {
+            proxies.no_proxy = no_proxy.clone();
+        }
+        if let Some((user, password)) = &self.proxy_credentials {
+            for proxy in [&mut proxies.http, &mut proxies.https]
+                .into_iter()
+                .flatten()
+            {
+                proxy.with_credentials(user, password);
+            }
+        }
+
+        Ok(HttpClient {
+            transport_security,
+            tls_config,
+            http_version_policy: self.http_version_policy,
+            http2_prior_knowledge: self.http2_prior_knowledge,
+            timeouts: self.timeouts,
+            proxies,
+            unix_socket: self.unix_socket.clone(),
+            resolver: self.resolver.clone(),
+            connect_attempt_delay: self.connect_attempt_delay,
+            redirect_policy: self.redirect_policy.clone(),
+            retry_policy: self.retry_policy.clone(),
+            decompression: self.decompression,
+            cookie_jar: self.cookie_jar.clone(),
+            digest_sessions: DigestSessions::default(),
+            oauth: self.oauth.clone().map(|oauth| match &self.oauth_origins {
+                Some(origins) => oauth.with_origins(origins.clone()),
+                None => oauth,
+            }),
+            aws_signer: self.aws_signer.clone(),
+            pool: Pool::new(self.pool_idle_timeout, self.pool_max_idle_per_host),
+        })
     }
 }
 
@@ -135,10 +667,28 @@ impl TransportSecurity {
 }
 
 /// HTTP client
+///
+/// Clones share the same connection pool.
 #[php_class(name = "Silq\\HttpClient")]
 #[derive(Clone)]
 pub struct HttpClient {
     transport_security: TransportSecurity,
+    tls_config: Arc<ClientConfig>,
+    http_version_policy: HttpVersionPolicy,
+    http2_prior_knowledge: bool,
+    timeouts: Timeouts,
+    proxies: Proxies,
+    unix_socket: Option<PathBuf>,
+    resolver: Resolver,
+    connect_attempt_delay: Duration,
+    redirect_policy: RedirectPolicy,
+    retry_policy: RetryPolicy,
+    decompression: bool,
+    cookie_jar: Option<CookieJar>,
+    digest_sessions: DigestSessions,
+    oauth: Option<OAuth2>,
+    aws_signer: Option<AwsSigner>,
+    pool: Pool,
 }
 
 #[php_impl]
@@ -197,72 +747,788 @@ impl HttpClient {
     pub fn trace(&self, uri: &str) -> PhpResult<RequestBuilder> {
         RequestBuilder::new(self.clone(), Method::TRACE, uri)
     }
-}
 
-enum Payload {
-    Empty,
-    Bytes(Vec<u8>),
-}
+    /// Send the given requests concurrently and wait for all of them. Returns, under the requests'
+    /// keys, their response or the exception they failed with.
+    ///
+    /// @param requests array<RequestBuilder>
+    /// @param concurrency int|null Maximum number of requests in flight, unlimited if null.
+    /// @param timeout float|null Time allowed for all the requests, those not completed by then
+    ///                           fail with a TimeoutException.
+    /// @return array<Response|\Exception>
+    pub fn send_all(
+        &self,
+        requests: &ZendHashTable,
+        concurrency: Option<usize>,
+        timeout: Option<f64>,
+    ) -> PhpResult<ZBox<ZendHashTable>> {
+        let deadline = parse_timeout(timeout)?.map(|timeout| Instant::now() + timeout);
+        let semaphore = match concurrency {
+            Some(0) => Err(SilqError::new("Concurrency must be at least 1".to_string()))?,
+            Some(concurrency) => Semaphore::new(concurrency.min(Semaphore::MAX_PERMITS)),
+            None => Semaphore::new(Semaphore::MAX_PERMITS),
+        };
+
+        let mut entries = requests
+            .iter()
+            .map(|(index, key, value)| (index, key, value.shallow_clone()))
+            .collect::<Vec<_>>();
+        // Validate all the requests before taking any builder apart
+        let mut seen = vec![];
+        let mut builders = vec![];
+        for (_, _, value) in &mut entries {
+            let object = value.object_mut().ok_or_else(|| {
+                SilqError::new("Requests must be RequestBuilder objects".to_string())
+            })?;
+            // Builders are taken apart when prepared
+            let handle = object as *const ZendObject;
+            if seen.contains(&handle) {
+                Err(SilqError::new(
+                    "The same RequestBuilder can't be sent twice".to_string(),
+                ))?
+            }
+            seen.push(handle);
+            let builder =
+                ZendClassObject::<RequestBuilder>::from_zend_obj_mut(object).ok_or_else(|| {
+                    SilqError::new("Requests must be RequestBuilder objects".to_string())
+                })?;
+            builders.push(builder);
+        }
+        let prepared = builders
+            .into_iter()
+            .map(|builder| {
+                let request = builder.prepare();
+                (&**builder, request)
+            })
+            .collect::<Vec<_>>();
+
+        let exchanges = prepared
+            .into_iter()
+            .map(|(builder, request)| {
+                let semaphore = &semaphore;
+                async move {
+                    let _permit = semaphore
+                        .acquire()
+                        .await
+                        .map_err(|err| SilqError::from("Unable to acquire permit", &err))?;
+                    builder.execute(request?).await
+                }
+            })
+            .collect();
+        let outcomes = future::block_on(concurrent::join_all(exchanges, deadline))?;
+
+        let mut responses = ZendHashTable::new();
+        for ((index, key, _), outcome) in entries.iter().zip(outcomes) {
+            let value = match outcome.unwrap_or(Err(SilqError::timeout(Timeout::Deadline))) {
+                Ok(response) => response
+                    .into_zval(false)
+                    .map_err(|err| SilqError::from("Unable to return response", &err))?,
+                Err(err) => err.to_exception()?,
+            };
+            match key {
+                Some(key) => responses.insert(key, value),
+                None => responses.insert_at_index(*index, value),
+            }
+            .map_err(|err| SilqError::from("Unable to return response", &err))?;
+        }
+        Ok(responses)
+    }
+}
+
+impl HttpClient {
+    /// Send the request to the target, over a pooled connection if `reuse` allows it.
+    ///
+    /// The connection is opened and the request sent on the runtime, so that they carry on while
+    /// PHP's thread doesn't poll the request, e.g. when sent in the background. Only the PHP DNS
+    /// resolver, if any, is called beforehand on PHP's thread.
+    async fn exchange(
+        &self,
+        target: &Target,
+        req: hyper::Request<RequestBody>,
+        timeouts: &Timeouts,
+        deadline: Option<Instant>,
+        reuse: bool,
+    ) -> Result<hyper::Response<Incoming>, SilqError> {
+        let key = target.pool_key();
+
+        let pooled = if reuse {
+            self.pool.checkout(&key)
+        } else {
+            None
+        };
+        let connector = match pooled {
+            Some(_) => None,
+            None => Some(self.connector(target)?),
+        };
+
+        let (target, timeouts, pool) = (target.clone(), *timeouts, self.pool.clone());
+        on_runtime(async move {
+            let reused = pooled.is_some();
+            let mut sender = match (pooled, connector) {
+                (Some(sender), _) => sender,
+                (None, Some(connector)) => {
+                    connector
+                        .open_connection(&target, &timeouts, deadline)
+                        .await?
+                }
+                (None, None) => unreachable!("connectors are made for unpooled requests"),
+            };
+
+            // Await the response...
+            let res = within(
+                Timeout::Response,
+                timeouts.response,
+                deadline,
+                sender.send_request(req),
+            )
+            .await?
+            .map_err(|err| match err.failure {
+                Some(Failure::Reset) if reused => err.with_failure(Failure::Stale),
+                _ => err,
+            })?;
+
+            pool.checkin(key, sender);
+
+            Ok(res)
+        })
+        .await
+    }
+
+    /// Connector opening a connection to the target, with the host names it needs resolved by
+    /// the PHP DNS resolver already.
+    fn connector(&self, target: &Target) -> Result<Connector, SilqError> {
+        let mut hosts = vec![];
+        match &target.proxy {
+            Some(proxy) => {
+                hosts.push(proxy.host.as_str());
+                if proxy.resolves_locally() {
+                    hosts.push(target.host.as_str());
+                }
+            }
+            None if target.unix_socket.is_none() => hosts.push(target.host.as_str()),
+            None => {}
+        }
+        let resolver = self
+            .resolver
+            .lookup(&hosts)
+            .map_err(|err| err.with_failure(Failure::Connect))?;
+        Ok(Connector {
+            tls_config: self.tls_config.clone(),
+            http_version_policy: self.http_version_policy,
+            http2_prior_knowledge: self.http2_prior_knowledge,
+            resolver,
+            connect_attempt_delay: self.connect_attempt_delay,
+        })
+    }
+}
+
+/// Run `future` on the runtime, independently of PHP's thread. Dropping the returned future
+/// aborts it.
+async fn on_runtime<T: Send + 'static>(
+    future: impl Future<Output = Result<T, SilqError>> + Send + 'static,
+) -> Result<T, SilqError> {
+    struct AbortOnDrop(AbortHandle);
+
+    impl Drop for AbortOnDrop {
+        fn drop(&mut self) {
+            self.0.abort();
+        }
+    }
+
+    let task = get_runtime().spawn(future);
+    let _abort = AbortOnDrop(task.abort_handle());
+    task.await
+        .map_err(|err| SilqError::from("Unable to send request", &err))?
+}
+
+/// Opens connections, without needing PHP's thread.
+struct Connector {
+    tls_config: Arc<ClientConfig>,
+    http_version_policy: HttpVersionPolicy,
+    http2_prior_knowledge: bool,
+    resolver: Lookup,
+    connect_attempt_delay: Duration,
+}
+
+impl Connector {
+    /// Open a new connection to the target's host.
+    async fn open_connection(
+        &self,
+        target: &Target,
+        timeouts: &Timeouts,
+        deadline: Option<Instant>,
+    ) -> Result<Sender, SilqError> {
+        if let Some(path) = &target.unix_socket {
+            #[cfg(unix)]
+            {
+                let stream = within(
+                    Timeout::Connect,
+                    timeouts.connect,
+                    deadline,
+                    UnixStream::connect(path),
+                )
+                .await?
+                .map_err(|err| {
+                    SilqError::from("Unable to establish connection", &err)
+                        .with_failure(Failure::Connect)
+                })?;
+                return self
+                    .establish(target, stream, timeouts, deadline, false)
+                    .await;
+            }
+            #[cfg(not(unix))]
+            Err(SilqError::new(format!(
+                "Unable to connect to {}: Unix domain sockets are not supported on this platform",
+                path.display()
+            )))?
+        }
+
+        let (host, port) = match &target.proxy {
+            Some(proxy) => (proxy.host.as_str(), proxy.port),
+            None => (target.host.as_str(), target.port),
+        };
+        let stream = within(Timeout::Connect, timeouts.connect, deadline, async {
+            let addresses = self.resolver.resolve(host, port).await?;
+            happy_eyeballs::connect(&addresses, self.connect_attempt_delay)
+                .await
+                .map_err(|err| SilqError::from("Unable to establish connection", &err))
+        })
+        .await?
+        .map_err(|err| err.with_failure(Failure::Connect))?;
+
+        let forward = target.forwards();
+
+        let stream = match &target.proxy {
+            Some(proxy) if !forward => {
+                within(
+                    Timeout::Connect,
+                    timeouts.connect,
+                    deadline,
+                    proxy.tunnel(stream, &target.host, target.port, &self.resolver),
+                )
+                .await??
+            }
+            _ => stream,
+        };
+
+        self.establish(target, stream, timeouts, deadline, forward)
+            .await
+    }
+
+    /// Secure the stream if needed, then perform the HTTP handshake.
+    async fn establish<S>(
+        &self,
+        target: &Target,
+        stream: S,
+        timeouts: &Timeouts,
+        deadline: Option<Instant>,
+        forward: bool,
+    ) -> Result<Sender, SilqError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        if target.scheme.eq("https") {
+            let connector = TlsConnector::from(self.tls_config.clone());
+            let name = ServerName::try_from(target.host.as_str())
+                .map_err(|err| SilqError::from("Unable to parse host", &err))?;
+            let stream = within(
+                Timeout::TlsHandshake,
+                timeouts.tls_handshake,
+                deadline,
+                connector.connect(name, stream),
+            )
+            .await?
+            .map_err(|err| SilqError::from("Connection error", &err))?;
+
+            let version = match stream.get_ref().1.alpn_protocol() {
+                Some(b"h2") => Version::HTTP_2,
+                _ if self.http_version_policy == HttpVersionPolicy::Http2Only => {
+                    Err(SilqError::new("Server does not support HTTP/2".to_string()))?
+                }
+                _ => Version::HTTP_11,
+            };
+            let handshake = handshake(stream, version, false);
+            within(Timeout::Deadline, None, deadline, handshake).await?
+        } else {
+            let version = if self.http2_prior_knowledge {
+                Version::HTTP_2
+            } else {
+                Version::HTTP_11
+            };
+            let handshake = handshake(stream, version, forward);
+            within(Timeout::Deadline, None, deadline, handshake).await?
+        }
+    }
+}
+
+/// Where a request is sent, derived from its URI and the client's configuration.
+#[derive(Clone)]
+struct Target {
+    scheme: Scheme,
+    address: String,
+    host: String,
+    port: u16,
+    proxy: Option<ProxyServer>,
+    unix_socket: Option<PathBuf>,
+    http2_prior_knowledge: bool,
+}
+
+impl Target {
+    fn new(
+        client: &HttpClient,
+        uri: &Uri,
+        unix_socket: Option<PathBuf>,
+    ) -> Result<Self, SilqError> {
+        let scheme = match uri.scheme() {
+            None => Err(SilqError::new("Missing URI scheme".to_string()))?,
+            Some(scheme) => (*scheme).to_owned(),
+        };
+
+        if !client.transport_security.allow_unsecure() && scheme.eq(&Scheme::HTTP) {
+            Err(SilqError::new("Unsecure HTTP disabled".to_string()))?
+        }
+
+        if client.http_version_policy == HttpVersionPolicy::Http2Only
+            && !client.http2_prior_knowledge
+            && scheme.eq(&Scheme::HTTP)
+        {
+            Err(SilqError::new(
+                "HTTP/2 can't be negotiated over unsecure HTTP".to_string(),
+            ))?
+        }
+
+        let default_port = if scheme.eq("https") { 443 } else { 80 };
+
+        let authority = uri
+            .authority()
+            .ok_or_else(|| SilqError::new("Unable to extract URI's authority".to_string()))?;
+
+        if authority.as_str().contains('@') {
+            Err(SilqError::new(
+                "Reject URI: contains username and password".to_string(),
+            ))?
+        }
+
+        let host = authority
+            .host()
+            .trim_start_matches('[')
+            .trim_end_matches(']');
+        let port = authority.port_u16().unwrap_or(default_port);
+
+        let address = format!("{}:{}", host, port);
+
+        let proxy = match unix_socket {
+            Some(_) => None,
+            None => client.proxies.select(&scheme, host).cloned(),
+        };
+
+        Ok(Self {
+            scheme,
+            address,
+            host: host.to_string(),
+            port,
+            proxy,
+            unix_socket,
+            http2_prior_knowledge: client.http2_prior_knowledge,
+        })
+    }
+
+    /// Whether the request goes through a proxy forwarding it, rather than tunneling it.
+    /// Cleartext HTTP/2 is always tunneled, forward proxies only speaking HTTP/1.1.
+    fn forwards(&self) -> bool {
+        match &self.proxy {
+            Some(proxy) => proxy.forwards(&self.scheme) && !self.http2_prior_knowledge,
+            None => false,
+        }
+    }
+
+    fn pool_key(&self) -> PoolKey {
+        let via = match (&self.unix_socket, &self.proxy) {
+            (Some(path), _) => Some(format!("unix:{}", path.display())),
+            (None, Some(proxy)) => Some(proxy.address.clone()),
+            (None, None) => None,
+        };
+        PoolKey::new(&self.scheme, &self.address, via)
+    }
+}
+
+/// Request taken out of its builder, ready to be sent.
+struct PreparedRequest {
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    payload: Payload,
+}
+
+#[php_class(name = "Silq\\RequestBuilder")]
+pub struct RequestBuilder {
+    client: HttpClient,
+    target: Target,
+    builder: Builder,
+    payload: Payload,
+    timeouts: Timeouts,
+    redirect_policy: RedirectPolicy,
+    retry_policy: RetryPolicy,
+    decompression: bool,
+    compression: Option<Compression>,
+    digest: Option<DigestAuth>,
+    aws_signer: Option<AwsSigner>,
+}
+
+impl RequestBuilder {
+    pub fn new(client: HttpClient, method: Method, uri: &str) -> PhpResult<Self> {
+        let uri = uri
+            .parse::<hyper::Uri>()
+            .map_err(|err| SilqError::from("Unable to parse URI", &err))?;
+
+        let target = Target::new(&client, &uri, client.unix_socket.clone())?;
+
+        // Create an HTTP request with an empty body and a HOST header
+        let authority = uri.authority().map(|authority| authority.to_string());
+        let builder = hyper::Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(HOST, authority.unwrap_or_default());
+
+        let timeouts = client.timeouts;
+        let redirect_policy = client.redirect_policy.clone();
+        let retry_policy = client.retry_policy.clone();
+        let decompression = client.decompression;
+        let aws_signer = client.aws_signer.clone();
+
+        Ok(Self {
+            client,
+            target,
+            builder,
+            payload: Payload::default(),
+            timeouts,
+            redirect_policy,
+            retry_policy,
+            decompression,
+            compression: None,
+            digest: None,
+            aws_signer,
+        })
+    }
+
+    /// Send the request to the target, retrying according to the retry policy. Counts the attempts
+    /// made in `attempts`.
+    async fn exchange(
+        &self,
+        target: &Target,
+        (method, uri, headers, payload): (&Method, &Uri, &HeaderMap, &Payload),
+        deadline: Option<Instant>,
+        attempts: &mut u32,
+    ) -> Result<hyper::Response<Incoming>, SilqError> {
+        let mut attempt = 0;
+        let mut reuse = true;
+        loop {
+            let (body, feeder) = payload.body().await?;
+            let mut req = hyper::Request::builder()
+                .method(method.clone())
+                .uri(uri.clone())
+                .body(body)
+                .map_err(|err| SilqError::from("Unable to build body", &err))?;
+            *req.headers_mut() = headers.clone();
+
+            if let Some(proxy) = target.proxy.as_ref().filter(|_| target.forwards()) {
+                // Tunneled requests are opaque to the proxy, only forwarded ones are authenticated
+                if let Some(authorization) = proxy.authorization()? {
+                    req.headers_mut().insert(PROXY_AUTHORIZATION, authorization);
+                }
+            }
+
+            attempt += 1;
+            *attempts += 1;
+            let outcome = feeding(
+                feeder,
+                self.client
+                    .exchange(target, req, &self.timeouts, deadline, reuse),
+            )
+            .await;
+
+            // The server closed the pooled connection as it was reused: send the request again
+            // once over a fresh connection, without counting the attempt. Requests the server may
+            // have received are only sent again if idempotent
+            let stale = match outcome.as_ref().err().and_then(|err| err.failure) {
+                Some(Failure::Unsent) => true,
+                Some(Failure::Stale) => method.is_idempotent(),
+                _ => false,
+            };
+            if stale && reuse && payload.is_replayable() {
+                reuse = false;
+                attempt -= 1;
+                *attempts -= 1;
+                continue;
+            }
+
+            let delay = match self.retry_policy.retry_delay(method, attempt, &outcome) {
+                // Streamed bodies can't be sent again
+                Some(delay) if payload.is_replayable() => delay,
+                _ => return outcome,
+            };
+            if deadline.is_some_and(|deadline| Instant::now() + delay >= deadline) {
+                return outcome;
+            }
+            drop(outcome);
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Take the request out of the builder, with the headers implied by its options.
+    fn prepare(&mut self) -> Result<PreparedRequest, SilqError> {
+        let builder = mem::replace(&mut self.builder, Builder::new());
+        let (parts, _) = builder
+            .body(())
+            .map_err(|err| SilqError::from("Unable to build request", &err))?
+            .into_parts();
+        let (method, uri, mut headers) = (parts.method, parts.uri, parts.headers);
+        let mut payload = mem::take(&mut self.payload);
+        // Empty payloads are sent as-is, without a body to decode
+        if let Some(compression) = self.compression.filter(|_| !payload.is_empty()) {
+            headers.insert(
+                CONTENT_ENCODING,
+                HeaderValue::from_static(compression.encoding.name()),
+            );
+            // Set from the compressed body when sending it, if known
+            headers.remove(CONTENT_LENGTH);
+            payload.compress(compression)?;
+        }
+        if self.decompression && !headers.contains_key(ACCEPT_ENCODING) {
+            headers.insert(ACCEPT_ENCODING, encoding::ACCEPT_ENCODING.clone());
+        }
 
-#[php_class(name = "Silq\\RequestBuilder")]
-pub struct RequestBuilder {
-    client: HttpClient,
-    scheme: Scheme,
-    address: String,
-    host: String,
-    builder: Builder,
-    payload: Payload,
-}
+        Ok(PreparedRequest {
+            method,
+            uri,
+            headers,
+            payload,
+        })
+    }
 
-impl RequestBuilder {
-    pub fn new(client: HttpClient, method: Method, uri: &str) -> PhpResult<Self> {
-        let uri = uri
-            .parse::<hyper::Uri>()
-            .map_err(|err| SilqError::from("Unable to parse URI", &err))?;
+    /// Copy of the request's options, to send a prepared request independently of the builder.
+    fn detach(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            target: self.target.clone(),
+            builder: Builder::new(),
+            payload: Payload::default(),
+            timeouts: self.timeouts,
+            redirect_policy: self.redirect_policy.clone(),
+            retry_policy: self.retry_policy.clone(),
+            decompression: self.decompression,
+            compression: self.compression,
+            digest: self.digest.clone(),
+            aws_signer: self.aws_signer.clone(),
+        }
+    }
 
-        let scheme = match uri.scheme() {
-            None => return Err(SilqError::new("Missing URI scheme".to_string()).into()),
-            Some(scheme) => (*scheme).to_owned(),
+    /// Send a request to the OAuth 2 token endpoint, returning the response's status and body.
+    async fn fetch_token(
+        &self,
+        uri: Uri,
+        mut headers: HeaderMap,
+        body: Vec<u8>,
+    ) -> Result<(StatusCode, Vec<u8>), SilqError> {
+        let mut client = self.client.clone();
+        client.oauth = None;
+        let authority = uri.authority().map(|authority| authority.to_string());
+        headers.insert(
+            HOST,
+            HeaderValue::try_from(authority.unwrap_or_default())
+                .map_err(|err| SilqError::from("Invalid token endpoint URI", &err))?,
+        );
+        let sender = Self {
+            target: Target::new(&client, &uri, client.unix_socket.clone())?,
+            client,
+            builder: Builder::new(),
+            payload: Payload::default(),
+            timeouts: self.timeouts,
+            redirect_policy: RedirectPolicy::None,
+            retry_policy: self.retry_policy.clone(),
+            decompression: self.decompression,
+            compression: None,
+            digest: None,
+            aws_signer: None,
         };
+        let request = PreparedRequest {
+            method: Method::POST,
+            uri,
+            headers,
+            payload: Payload::bytes(body),
+        };
+        // Boxed, as executing a request may fetch a token
+        let exchange: future::Exchange = Box::pin(async move { sender.execute(request).await });
+        let mut response = exchange.await?;
+        let body = match response.body.take() {
+            Some(mut body) => body.collect().await?,
+            None => vec![],
+        };
+        Ok((response.parts.status, body))
+    }
 
-        if !client.transport_security.allow_unsecure() && scheme.eq(&Scheme::HTTP) {
-            Err(SilqError::new("Unsecure HTTP disabled".to_string()))?
-        }
-
-        let default_port = if scheme.eq("https") { 443 } else { 80 };
+    /// Send the prepared request, following redirects, and return the response.
+    async fn execute(&self, request: PreparedRequest) -> Result<Response, SilqError> {
+        let deadline = self.timeouts.deadline();
+        let PreparedRequest {
+            mut method,
+            mut uri,
+            mut headers,
+            mut payload,
+        } = request;
+
+        let mut target = self.target.clone();
+        let initiator = uri.clone();
+        let mut redirects = vec![];
+        let mut attempts = 0;
+        // Digest or OAuth 2 authorization of the current URI, and number of 401 answered for it
+        let mut authorization = None;
+        let mut challenges = 0;
+        let res = loop {
+            // Credentials are only sent to the origin they were given for
+            let same_origin = redirect::origin(&uri) == redirect::origin(&initiator);
+            let digest = self.digest.as_ref().filter(|_| same_origin);
+            let oauth = self.client.oauth.as_ref().filter(|oauth| {
+                oauth.covers(&uri)
+                    && self.digest.is_none()
+                    && self.aws_signer.is_none()
+                    && !headers.contains_key(AUTHORIZATION)
+            });
+            if let (Some(digest), None) = (digest, &authorization) {
+                authorization =
+                    digest.preemptive(&self.client.digest_sessions, (&method, &uri, &payload))?;
+            }
+            if let (Some(oauth), None) = (oauth, &authorization) {
+                let fetch = |uri, headers, body| self.fetch_token(uri, headers, body);
+                authorization = Some(oauth.authorization(None, fetch).await?);
+            }
+            let res = {
+                let mut headers = match &self.client.cookie_jar {
+                    Some(jar) => Cow::Owned(jar.apply(&headers, &method, &uri, &initiator)?),
+                    None => Cow::Borrowed(&headers),
+                };
+                if let Some(authorization) = &authorization {
+                    headers
+                        .to_mut()
+                        .insert(AUTHORIZATION, authorization.clone());
+                }
+                // Signed last, covering the headers as sent
+                if let Some(signer) = &self.aws_signer {
+                    let body = payload.to_bytes();
+                    signer.sign(&method, &uri, headers.to_mut(), body.as_deref())?;
+                }
+                self.exchange(
+                    &target,
+                    (&method, &uri, &*headers, &payload),
+                    deadline,
+                    &mut attempts,
+                )
+                .await?
+            };
+            if let Some(jar) = &self.client.cookie_jar {
+                jar.store(&uri, res.headers());
+            }
 
-        let authority = uri
-            .authority()
-            .ok_or_else(|| SilqError::new("Unable to extract URI's authority".to_string()))?
-            .clone();
+            // Answer a fresh challenge once, and again only to renew a stale nonce
+            if let Some(digest) = digest.filter(|_| {
+                res.status() == StatusCode::UNAUTHORIZED
+                    && payload.is_replayable()
+                    && challenges < 2
+            }) {
+                let answer = digest.answer(
+                    &self.client.digest_sessions,
+                    res.headers(),
+                    (&method, &uri, &payload),
+                )?;
+                if let Some((answer, _)) = answer.filter(|(_, stale)| challenges == 0 || *stale) {
+                    authorization = Some(answer);
+                    challenges += 1;
+                    continue;
+                }
+            }
+            // The token may have been revoked, fetch another one once
+            if let Some(oauth) = oauth.filter(|_| {
+                res.status() == StatusCode::UNAUTHORIZED
+                    && payload.is_replayable()
+                    && challenges == 0
+            }) {
+                drop(res);
+                let fetch = |uri, headers, body| self.fetch_token(uri, headers, body);
+                authorization = Some(oauth.authorization(authorization.as_ref(), fetch).await?);
+                challenges += 1;
+                continue;
+            }
 
-        if authority.as_str().contains('@') {
-            Err(SilqError::new(
-                "Reject URI: contains username and password".to_string(),
-            ))?
-        }
+            let next = match redirect::location(res.status(), res.headers(), &uri) {
+                None => break res,
+                Some(next) => next?,
+            };
+            // Streamed bodies can't be sent again, the caller has to handle the redirect
+            if redirect::keeps_body(res.status(), &method) && !payload.is_replayable() {
+                break res;
+            }
+            if !self
+                .redirect_policy
+                .follows(res.status(), &next, &redirects)?
+            {
+                break res;
+            }
+            if redirect::is_downgrade(&uri, &next)
+                && !self.client.transport_security.allow_unsecure()
+            {
+                Err(SilqError::new(format!(
+                    "Refusing to follow redirect from HTTPS to unsecure HTTP: {next}"
+                )))?
+            }
 
-        let host = authority
-            .host()
-            .trim_start_matches('[')
-            .trim_end_matches(']');
-        let port = authority.port_u16().unwrap_or(default_port);
+            redirect::rewrite(
+                res.status(),
+                &mut method,
+                &mut headers,
+                &mut payload,
+                &uri,
+                &next,
+            );
+            let authority = next.authority().map(|authority| authority.to_string());
+            headers.insert(
+                HOST,
+                HeaderValue::try_from(authority.unwrap_or_default())
+                    .map_err(|err| SilqError::from("Invalid redirect location", &err))?,
+            );
+            // The socket serves the initial origin only, other ones are reached over TCP
+            let unix_socket = self
+                .target
+                .unix_socket
+                .clone()
+                .filter(|_| redirect::origin(&next) == redirect::origin(&initiator));
+            target = Target::new(&self.client, &next, unix_socket)?;
+            redirects.push(mem::replace(&mut uri, next));
+            authorization = None;
+            challenges = 0;
+        };
 
-        let address = format!("{}:{}", host, port);
+        let (parts, body) = res.into_parts();
 
-        // Create an HTTP request with an empty body and a HOST header
-        let builder = hyper::Request::builder()
-            .method(method)
-            .uri(uri)
-            .header(hyper::header::HOST, authority.as_str());
+        // Bodies with unknown codings are left as-is
+        let encodings = Encoding::from_headers(&parts.headers).filter(|_| self.decompression);
+        let decoding = match encodings {
+            Some(encodings) if !encodings.is_empty() => Some(Decoding::new(&encodings)?),
+            _ => None,
+        };
+        let decompressed = decoding.is_some();
 
-        Ok(Self {
-            client,
-            scheme,
-            address,
-            host: host.to_string(),
-            builder,
-            payload: Payload::Empty,
+        Ok(Response {
+            parts,
+            body: Some(ResponseBody::new(
+                body,
+                self.timeouts.read,
+                deadline,
+                decoding,
+            )),
+            uri,
+            redirects,
+            attempts,
+            decompressed,
         })
     }
 
@@ -359,7 +1625,7 @@ impl RequestBuilder {
         #[this] this: &mut ZendClassObject<Self>,
         bytes: Binary<u8>,
     ) -> PhpResult<&mut ZendClassObject<Self>> {
-        this.payload = Payload::Bytes(bytes.to_vec());
+        this.payload = Payload::bytes(bytes.to_vec());
         Ok(this)
     }
 
@@ -373,7 +1639,7 @@ impl RequestBuilder {
     ) -> PhpResult<&'a mut ZendClassObject<Self>> {
         let request_headers = this.get_mut_headers()?;
         request_headers.insert(CONTENT_TYPE, CONTENT_TYPE_JSON.clone());
-        this.payload = Payload::Bytes(
+        this.payload = Payload::bytes(
             serde_json::to_string(&ZvalSerializer(body))
                 .map_err(|err| SilqError::from("Unable to encode value to JSON", &err))?
                 .into_bytes(),
@@ -391,7 +1657,7 @@ impl RequestBuilder {
     ) -> PhpResult<&'a mut ZendClassObject<Self>> {
         let request_headers = this.get_mut_headers()?;
         request_headers.insert(CONTENT_TYPE, CONTENT_TYPE_FORM.clone());
-        this.payload = Payload::Bytes(
+        this.payload = Payload::bytes(
             serde_urlencoded::to_string(ZvalSerializer(body))
                 .map_err(|err| SilqError::from("Unable to encode value to url encoded form", &err))?
                 .into_bytes(),
@@ -399,6 +1665,82 @@ impl RequestBuilder {
         Ok(this)
     }
 
+    /// Stream the given file as request's body, with its size as content-length.
+    ///
+    /// @param path string
+    /// @return RequestBuilder
+    pub fn with_body_from_file(
+        #[this] this: &mut ZendClassObject<Self>,
+        path: &str,
+    ) -> PhpResult<&mut ZendClassObject<Self>> {
+        let path = PathBuf::from(path);
+        if !path.is_file() {
+            Err(SilqError::new(format!(
+                "Unable to read file {}: not a file",
+                path.display()
+            )))?
+        }
+        this.payload = Payload::new(vec![Source::File(path)]);
+        Ok(this)
+    }
+
+    /// Stream the given PHP stream as request's body, from its current position. Sent with
+    /// chunked transfer encoding unless the length is given. The body can't be sent again, so
+    /// the request isn't retried and redirects requiring the body are returned as-is.
+    ///
+    /// The stream is switched to non-blocking mode, so that waiting for its data doesn't block
+    /// other requests. Streams not supporting it, e.g. some user-space wrappers, are rejected.
+    ///
+    /// @param stream resource
+    /// @param length int|null Number of bytes to read from the stream.
+    /// @return RequestBuilder
+    pub fn with_body_from_stream<'a>(
+        #[this] this: &'a mut ZendClassObject<Self>,
+        stream: &Zval,
+        length: Option<u64>,
+    ) -> PhpResult<&'a mut ZendClassObject<Self>> {
+        this.payload = Payload::new(vec![Source::stream(stream, length)?]);
+        Ok(this)
+    }
+
+    /// Stream the chunks yielded by the given Generator as request's body, or returned by the
+    /// given callable until it returns null or an empty string. Sent with chunked transfer
+    /// encoding unless the length is given. The body can't be sent again, so the request isn't
+    /// retried and redirects requiring the body are returned as-is.
+    ///
+    /// @param generator Generator|callable
+    /// @param length int|null Total number of bytes produced.
+    /// @return RequestBuilder
+    pub fn with_body_from_generator<'a>(
+        #[this] this: &'a mut ZendClassObject<Self>,
+        generator: &Zval,
+        length: Option<u64>,
+    ) -> PhpResult<&'a mut ZendClassObject<Self>> {
+        this.payload = Payload::new(vec![Source::generator(generator, length)?]);
+        Ok(this)
+    }
+
+    /// Add given multipart form as request's body, files being streamed from disk. Set the
+    /// content-type header accordingly.
+    ///
+    /// @param multipart Multipart
+    /// @return RequestBuilder
+    pub fn with_multipart<'a>(
+        #[this] this: &'a mut ZendClassObject<Self>,
+        multipart: &ZendClassObject<Multipart>,
+    ) -> PhpResult<&'a mut ZendClassObject<Self>> {
+        let request_headers = this.get_mut_headers()?;
+        request_headers.insert(
+            CONTENT_TYPE,
+            multipart
+                .content_type()
+                .try_into()
+                .map_err(|err| SilqError::from("Unable to encode content type", &err))?,
+        );
+        this.payload = multipart.payload();
+        Ok(this)
+    }
+
     /// Add basic authentication header with given user/password.
     ///
     /// @param user string user's name
@@ -420,123 +1762,198 @@ impl RequestBuilder {
         Ok(this)
     }
 
-    /// Send the request and return response.
+    /// Add bearer authentication header with given token, e.g. an OAuth 2 access token.
     ///
-    /// @return Response
-    pub fn send(&mut self) -> PhpResult<Response> {
-        let rt = get_runtime();
-
-        let address = self.address.clone();
-        let builder = std::mem::replace(&mut self.builder, Builder::new());
-
-        let req = match mem::replace(&mut self.payload, Payload::Empty) {
-            Payload::Empty => builder.body(Full::<Bytes>::from(Bytes::new())),
-            Payload::Bytes(bytes) => builder.body(Full::<Bytes>::from(bytes)),
-        }
-        .map_err(|err| SilqError::from("Unable to build body", &err))?;
-
-        let res = rt.block_on(async move {
-            let mut sender = if self.scheme.eq("https") {
-                let root_store = {
-                    let mut root_store = RootCertStore::empty();
-                    if let TransportSecurity::SecureOnly {
-                        ca_cert: Some(ca_cert),
-                        ..
-                    } = &self.client.transport_security
-                    {
-                        root_store.add(&ca_cert.certificate).map_err(|err| {
-                            SilqError::from("Unable to use specified CA Certificate", &err)
-                        })?;
-                    } else {
-                        root_store.add_trust_anchors(TLS_SERVER_ROOTS.iter().map(|ta| {
-                            OwnedTrustAnchor::from_subject_spki_name_constraints(
-                                ta.subject,
-                                ta.spki,
-                                ta.name_constraints,
-                            )
-                        }));
-                    }
-                    root_store
-                };
+    /// @param token string
+    /// @return RequestBuilder
+    pub fn with_bearer_token<'a>(
+        #[this] this: &'a mut ZendClassObject<Self>,
+        token: &str,
+    ) -> PhpResult<&'a mut ZendClassObject<Self>> {
+        let request_headers = this.get_mut_headers()?;
+        request_headers.insert(
+            AUTHORIZATION,
+            format!("Bearer {token}")
+                .try_into()
+                .map_err(|err| SilqError::from("Unable to encode token as header value", &err))?,
+        );
+        Ok(this)
+    }
 
-                let tls_config = match &self.client.transport_security {
-                    TransportSecurity::SecureOnly {
-                        client_identity: Some(identity),
-                        ..
-                    } => ClientConfig::builder()
-                        .with_safe_defaults()
-                        .with_root_certificates(root_store)
-                        .with_client_auth_cert(
-                            vec![identity.certificate.clone()],
-                            identity.private_key.clone(),
-                        )
-                        .map_err(|err| SilqError::from("Unable to use client identity", &err))?,
-
-                    _ => ClientConfig::builder()
-                        .with_safe_defaults()
-                        .with_root_certificates(root_store)
-                        .with_no_client_auth(),
-                };
+    /// Sign the request with AWS Signature Version 4, overriding the client's signer.
+    ///
+    /// @param signer AwsSigner
+    /// @return RequestBuilder
+    pub fn with_aws_signer<'a>(
+        #[this] this: &'a mut ZendClassObject<Self>,
+        signer: &ZendClassObject<AwsSigner>,
+    ) -> &'a mut ZendClassObject<Self> {
+        this.aws_signer = Some((*signer).clone());
+        this
+    }
 
-                let rc_tls_config = Arc::new(tls_config);
-
-                let connector = TlsConnector::from(rc_tls_config);
-                let name = ServerName::try_from(self.host.as_str())
-                    .map_err(|err| SilqError::from("Unable to parse host", &err))?;
-                let stream = TcpStream::connect(&address)
-                    .await
-                    .map_err(|err| SilqError::from("Unable to establish connection", &err))?;
-                let stream = connector
-                    .connect(name, stream)
-                    .await
-                    .map_err(|err| SilqError::from("Connection error", &err))?;
-                let stream = TokioIo::new(stream);
-
-                // Perform a TCP handshake
-                let (sender, conn) = hyper::client::conn::http1::handshake(stream)
-                    .await
-                    .map_err(|err| SilqError::from("Unable to run handshake", &err))?;
-
-                // spawn a task to poll the connection and drive the HTTP state
-                tokio::task::spawn(async move {
-                    conn.await
-                        .map_err(|err| SilqError::from("Unable to poll connection", &err))
-                });
-
-                sender
-            } else {
-                let stream = TcpStream::connect(address)
-                    .await
-                    .map_err(|err| SilqError::from("Unable to establish connection", &err))?;
-                let stream = TokioIo::new(stream);
-
-                // Perform a TCP handshake
-                let (sender, conn) = hyper::client::conn::http1::handshake(stream)
-                    .await
-                    .map_err(|err| SilqError::from("Unable to run handshake", &err))?;
-
-                // spawn a task to poll the connection and drive the HTTP state
-                tokio::task::spawn(async move {
-                    conn.await
-                        .map_err(|err| SilqError::from("Unable to poll connection", &err))
-                });
-
-                sender
-            };
+    /// Answer Digest authentication challenges (RFC 7616) with the given user/password, sending
+    /// the request again after a `401` response. Later requests to the same origin reuse the
+    /// challenge's nonce. Streamed bodies can't be sent again, their `401` response is returned.
+    ///
+    /// @param user string user's name
+    /// @param password string password
+    /// @return RequestBuilder
+    pub fn with_digest_auth<'a>(
+        #[this] this: &'a mut ZendClassObject<Self>,
+        user: &str,
+        password: &str,
+    ) -> &'a mut ZendClassObject<Self> {
+        this.digest = Some(DigestAuth::new(user, password));
+        this
+    }
 
-            // Await the response...
-            sender
-                .send_request(req)
-                .await
-                .map_err(|err| SilqError::from("Unable to send request", &err))
-        })?;
+    /// Connect to the Unix domain socket at the given path instead of the URI's host, which is
+    /// still used for the `Host` header and TLS. Proxies are ignored.
+    ///
+    /// @param path string
+    /// @return RequestBuilder
+    pub fn with_unix_socket<'a>(
+        #[this] this: &'a mut ZendClassObject<Self>,
+        path: &str,
+    ) -> &'a mut ZendClassObject<Self> {
+        this.target.unix_socket = Some(PathBuf::from(path));
+        this.target.proxy = None;
+        this
+    }
 
-        let (parts, body) = res.into_parts();
+    /// Override the client's redirect policy.
+    ///
+    /// @param policy int|callable(string $uri, int $status, string[] $redirects): bool
+    /// @return RequestBuilder
+    pub fn with_redirect_policy<'a>(
+        #[this] this: &'a mut ZendClassObject<Self>,
+        policy: &Zval,
+    ) -> PhpResult<&'a mut ZendClassObject<Self>> {
+        this.redirect_policy = RedirectPolicy::parse(policy)?;
+        Ok(this)
+    }
 
-        Ok(Response {
-            parts,
-            body: Some(body),
-        })
+    /// Override the client's retry policy.
+    ///
+    /// @param policy RetryPolicy
+    /// @return RequestBuilder
+    pub fn with_retry_policy<'a>(
+        #[this] this: &'a mut ZendClassObject<Self>,
+        policy: &ZendClassObject<RetryPolicy>,
+    ) -> &'a mut ZendClassObject<Self> {
+        this.retry_policy = (*policy).clone();
+        this
+    }
+
+    /// Override whether the client decodes compressed response bodies.
+    ///
+    /// @param enable bool
+    /// @return RequestBuilder
+    pub fn with_decompression(
+        #[this] this: &mut ZendClassObject<Self>,
+        enable: bool,
+    ) -> &mut ZendClassObject<Self> {
+        this.decompression = enable;
+        this
+    }
+
+    /// Compress the request's body, setting the `Content-Encoding` header accordingly.
+    ///
+    /// @param codec string One of gzip, deflate, br or zstd.
+    /// @param level int|null Compression level, from 0 to 9 for gzip and deflate, 0 to 11 for br
+    ///                       and 1 to 22 for zstd. Defaults to the codec's usual level.
+    /// @return RequestBuilder
+    pub fn with_compression(
+        #[this] this: &mut ZendClassObject<Self>,
+        codec: &str,
+        level: Option<i64>,
+    ) -> PhpResult<&mut ZendClassObject<Self>> {
+        this.compression = Some(Compression::parse(codec, level)?);
+        Ok(this)
+    }
+
+    /// Override the client's time allowed to establish the TCP connection.
+    ///
+    /// @param seconds float|null Timeout, null waits indefinitely.
+    /// @return RequestBuilder
+    pub fn with_connect_timeout(
+        #[this] this: &mut ZendClassObject<Self>,
+        seconds: Option<f64>,
+    ) -> PhpResult<&mut ZendClassObject<Self>> {
+        this.timeouts.connect = parse_timeout(seconds)?;
+        Ok(this)
+    }
+
+    /// Override the client's time allowed to complete the TLS handshake.
+    ///
+    /// @param seconds float|null Timeout, null waits indefinitely.
+    /// @return RequestBuilder
+    pub fn with_tls_handshake_timeout(
+        #[this] this: &mut ZendClassObject<Self>,
+        seconds: Option<f64>,
+    ) -> PhpResult<&mut ZendClassObject<Self>> {
+        this.timeouts.tls_handshake = parse_timeout(seconds)?;
+        Ok(this)
+    }
+
+    /// Override the client's time allowed between sending the request and receiving the
+    /// response's head.
+    ///
+    /// @param seconds float|null Timeout, null waits indefinitely.
+    /// @return RequestBuilder
+    pub fn with_response_timeout(
+        #[this] this: &mut ZendClassObject<Self>,
+        seconds: Option<f64>,
+    ) -> PhpResult<&mut ZendClassObject<Self>> {
+        this.timeouts.response = parse_timeout(seconds)?;
+        Ok(this)
+    }
+
+    /// Override the client's idle time allowed between two frames of the response's body.
+    ///
+    /// @param seconds float|null Timeout, null waits indefinitely.
+    /// @return RequestBuilder
+    pub fn with_read_timeout(
+        #[this] this: &mut ZendClassObject<Self>,
+        seconds: Option<f64>,
+    ) -> PhpResult<&mut ZendClassObject<Self>> {
+        this.timeouts.read = parse_timeout(seconds)?;
+        Ok(this)
+    }
+
+    /// Override the client's time allowed for the whole exchange, from sending the request to
+    /// reading the end of the response's body.
+    ///
+    /// @param seconds float|null Timeout, null waits indefinitely.
+    /// @return RequestBuilder
+    pub fn with_timeout(
+        #[this] this: &mut ZendClassObject<Self>,
+        seconds: Option<f64>,
+    ) -> PhpResult<&mut ZendClassObject<Self>> {
+        this.timeouts.total = parse_timeout(seconds)?;
+        Ok(this)
+    }
+
+    /// Send the request and return response.
+    ///
+    /// @return Response
+    pub fn send(&mut self) -> PhpResult<Response> {
+        let request = self.prepare()?;
+        Ok(future::block_on(self.execute(request))??)
+    }
+
+    /// Start sending the request and return without waiting for the response. The request is sent
+    /// in the background, while redirects, retries and PHP callbacks carry on whenever the
+    /// extension waits, e.g. in `Future::wait()` or another request's `send()`.
+    ///
+    /// @return Future
+    pub fn send_async(&mut self) -> PhpResult<ResponseFuture> {
+        let request = self.prepare()?;
+        let sender = self.detach();
+        Ok(ResponseFuture::spawn(async move {
+            sender.execute(request).await
+        }))
     }
 }
 
@@ -544,7 +1961,11 @@ impl RequestBuilder {
 #[php_class(name = "Silq\\Response")]
 pub struct Response {
     parts: Parts,
-    body: Option<Incoming>,
+    body: Option<ResponseBody>,
+    uri: Uri,
+    redirects: Vec<Uri>,
+    attempts: u32,
+    decompressed: bool,
 }
 
 #[php_impl]
@@ -559,6 +1980,32 @@ impl Response {
         self.parts.status.as_u16()
     }
 
+    /// Returns the HTTP version negotiated with the server, e.g. `HTTP/1.1` or `HTTP/2.0`
+    pub fn get_http_version(&self) -> String {
+        format!("{:?}", self.parts.version)
+    }
+
+    /// Returns the URI the response comes from, after following redirects
+    pub fn get_uri(&self) -> String {
+        self.uri.to_string()
+    }
+
+    /// Returns the URIs which redirected to the next one, in the order they were requested
+    pub fn get_redirects(&self) -> Vec<String> {
+        self.redirects.iter().map(Uri::to_string).collect()
+    }
+
+    /// Returns the number of attempts made to get the response, redirects included
+    pub fn get_attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Whether the body is decoded from its `Content-Encoding`. Headers are left as received, so
+    /// `Content-Encoding` and `Content-Length` describe the encoded body.
+    pub fn is_decompressed(&self) -> bool {
+        self.decompressed
+    }
+
     pub fn is_success(&self) -> bool {
         self.parts.status.is_success()
     }
@@ -599,24 +2046,32 @@ impl Response {
         HeaderIterator::new(self.parts.headers.clone())
     }
 
+    /// Returns the cookies set by the `Set-Cookie` headers. Invalid cookies are skipped and
+    /// invalid attributes ignored, see `getCookieErrors()`.
+    ///
+    /// @return Cookie[]
+    pub fn get_cookies(&self) -> Vec<SetCookie> {
+        SetCookie::parse_all(&self.parts.headers, &mut vec![])
+    }
+
+    /// Returns why cookies or some of their attributes were ignored by `getCookies()`.
+    ///
+    /// @return string[]
+    pub fn get_cookie_errors(&self) -> Vec<String> {
+        let mut errors = vec![];
+        SetCookie::parse_all(&self.parts.headers, &mut errors);
+        errors
+    }
+
     /// Download body as raw bytes.
     pub fn get_bytes(&mut self) -> PhpResult<Binary<u8>> {
-        let runtime = get_runtime();
-        runtime.block_on(async {
+        future::block_on(async {
             let mut body = self
                 .body
                 .take()
                 .ok_or_else(|| SilqError::new("Body already consumed".into()))?;
-            let mut content = vec![];
-            while let Some(next) = body.frame().await {
-                let frame =
-                    next.map_err(|err| SilqError::from("Unable to fetch next frame", &err))?;
-                if let Some(chunk) = frame.data_ref() {
-                    content.extend_from_slice(chunk);
-                }
-            }
-            Ok(Binary::from(content))
-        })
+            Ok(Binary::from(body.collect().await?))
+        })?
     }
 
     /// Download body as utf-8 string.
@@ -633,6 +2088,37 @@ impl Response {
         }
     }
 
+    /// Download body to the given file, without loading it in memory. Returns the number of bytes
+    /// written.
+    ///
+    /// @param path string
+    /// @param atomic bool [default: false] Write to a temporary file renamed to `path` once the
+    ///                    body is complete, leaving `path` untouched on failure.
+    /// @param append bool [default: false] Append to the file, e.g. to resume a download with a
+    ///                    `Range` request. Content written before a failure is kept.
+    /// @param max_size int|null Maximum number of bytes to write, failing past it.
+    /// @return int
+    pub fn save_to(
+        &mut self,
+        path: &str,
+        atomic: Option<bool>,
+        append: Option<bool>,
+        max_size: Option<u64>,
+    ) -> PhpResult<u64> {
+        let options = SaveOptions {
+            atomic: atomic.unwrap_or(false),
+            append: append.unwrap_or(false),
+            max_size,
+        };
+        future::block_on(async {
+            let mut body = self
+                .body
+                .take()
+                .ok_or_else(|| SilqError::new("Body already consumed".into()))?;
+            Ok(body.save(Path::new(path), options).await?)
+        })?
+    }
+
     pub fn iter_frames(&mut self) -> PhpResult<FrameIterator> {
         Ok(FrameIterator::new(self.body.take().ok_or_else(|| {
             SilqError::new("Body already consumed".into())
@@ -753,14 +2239,14 @@ enum FrameIteratorState {
 #[php_class(name = "Silq\\FrameIterator")]
 #[implements(ce::iterator())]
 pub struct FrameIterator {
-    incoming: Incoming,
+    body: ResponseBody,
     state: FrameIteratorState,
 }
 
 impl FrameIterator {
-    fn new(incoming: Incoming) -> Self {
+    fn new(body: ResponseBody) -> Self {
         Self {
-            incoming,
+            body,
             state: FrameIteratorState::Uninitialized,
         }
     }
@@ -774,25 +2260,21 @@ impl FrameIterator {
             FrameIteratorState::Frame { index, .. } => index + 1,
         };
 
-        let runtime = get_runtime();
-        match runtime.block_on(async { self.incoming.frame().await }) {
-            Some(Ok(frame)) => {
+        match future::block_on(self.body.next_chunk())? {
+            Ok(Some(chunk)) => {
                 self.state = FrameIteratorState::Frame {
-                    frame: frame
-                        .into_data()
-                        .map_err(|_| SilqError::new("Unable to decode frame".into()))?
-                        .to_vec(),
+                    frame: chunk.to_vec(),
                     index,
                 };
                 Ok(())
             }
-            None => {
+            Ok(None) => {
                 self.state = FrameIteratorState::Terminated;
                 Ok(())
             }
-            Some(Err(err)) => {
+            Err(err) => {
                 self.state = FrameIteratorState::Terminated;
-                Err(SilqError::from("Unable to fetch next frame", &err).into())
+                Err(err.into())
             }
         }
     }
@@ -934,7 +2416,12 @@ fn startup() {
         .expect("Unable to set global runtime");
 }
 
+extern "C" fn request_shutdown(_type: i32, _module_number: i32) -> i32 {
+    event_loop::reset();
+    0
+}
+
 #[php_module]
 pub fn module(module: ModuleBuilder) -> ModuleBuilder {
-    module
+    module.request_shutdown_function(request_shutdown)
 }
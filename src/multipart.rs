@@ -0,0 +1,190 @@
+//! `multipart/form-data` payloads (RFC 7578), with text fields and files.
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::path::{Path, PathBuf};
+
+use ext_php_rs::{binary::Binary, prelude::*, types::ZendClassObject};
+use hyper::body::Bytes;
+
+use crate::{
+    error::SilqError,
+    payload::{Payload, Source},
+};
+
+const DEFAULT_FILE_CONTENT_TYPE: &str = "application/octet-stream";
+
+#[derive(Clone)]
+struct Part {
+    name: String,
+    filename: Option<String>,
+    content_type: Option<String>,
+    content: Source,
+}
+
+impl Part {
+    fn headers(&self, boundary: &str) -> String {
+        let mut headers = format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"{}\"",
+            escape(&self.name)
+        );
+        if let Some(filename) = &self.filename {
+            headers.push_str(&format!("; filename=\"{}\"", escape(filename)));
+        }
+        if let Some(content_type) = &self.content_type {
+            headers.push_str(&format!("\r\nContent-Type: {content_type}"));
+        }
+        headers.push_str("\r\n\r\n");
+        headers
+    }
+}
+
+/// Builder of `multipart/form-data` payloads, sent with `RequestBuilder::withMultipart()`.
+///
+/// Files given by path are streamed from disk while the request is sent.
+#[php_class(name = "Silq\\Multipart")]
+#[derive(Clone)]
+pub struct Multipart {
+    boundary: String,
+    parts: Vec<Part>,
+}
+
+impl Multipart {
+    /// Value of the `Content-Type` header.
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    pub fn payload(&self) -> Payload {
+        let mut sources = vec![];
+        for part in &self.parts {
+            sources.push(Source::Bytes(Bytes::from(part.headers(&self.boundary))));
+            sources.push(part.content.clone());
+            sources.push(Source::Bytes(Bytes::from_static(b"\r\n")));
+        }
+        sources.push(Source::Bytes(Bytes::from(format!(
+            "--{}--\r\n",
+            self.boundary
+        ))));
+        Payload::new(sources)
+    }
+}
+
+impl Default for Multipart {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[php_impl]
+impl Multipart {
+    #[constructor]
+    pub fn new() -> Self {
+        let random = [(); 2].map(|_| RandomState::new().build_hasher().finish());
+        Self {
+            boundary: format!("silq-{:016x}{:016x}", random[0], random[1]),
+            parts: vec![],
+        }
+    }
+
+    /// Returns the boundary delimiting the parts.
+    pub fn get_boundary(&self) -> String {
+        self.boundary.clone()
+    }
+
+    /// Add a text field.
+    ///
+    /// @param name string
+    /// @param value string
+    /// @return Multipart
+    pub fn with_field<'a>(
+        #[this] this: &'a mut ZendClassObject<Self>,
+        name: &str,
+        value: Binary<u8>,
+    ) -> &'a mut ZendClassObject<Self> {
+        this.parts.push(Part {
+            name: name.to_string(),
+            filename: None,
+            content_type: None,
+            content: Source::Bytes(Bytes::from(value.to_vec())),
+        });
+        this
+    }
+
+    /// Add a file read from disk when the request is sent.
+    ///
+    /// @param name string
+    /// @param path string
+    /// @param filename string|null [default: the path's file name]
+    /// @param content_type string|null [default: application/octet-stream]
+    /// @return Multipart
+    pub fn with_file<'a>(
+        #[this] this: &'a mut ZendClassObject<Self>,
+        name: &str,
+        path: &str,
+        filename: Option<String>,
+        content_type: Option<String>,
+    ) -> PhpResult<&'a mut ZendClassObject<Self>> {
+        let path = PathBuf::from(path);
+        if !path.is_file() {
+            Err(SilqError::new(format!(
+                "Unable to read file {}: not a file",
+                path.display()
+            )))?
+        }
+        let filename = filename.unwrap_or_else(|| file_name(&path));
+        this.parts.push(Part {
+            name: name.to_string(),
+            filename: Some(filename),
+            content_type: Some(parse_content_type(content_type)?),
+            content: Source::File(path),
+        });
+        Ok(this)
+    }
+
+    /// Add a file from its contents.
+    ///
+    /// @param name string
+    /// @param contents string
+    /// @param filename string
+    /// @param content_type string|null [default: application/octet-stream]
+    /// @return Multipart
+    pub fn with_file_contents<'a>(
+        #[this] this: &'a mut ZendClassObject<Self>,
+        name: &str,
+        contents: Binary<u8>,
+        filename: &str,
+        content_type: Option<String>,
+    ) -> PhpResult<&'a mut ZendClassObject<Self>> {
+        this.parts.push(Part {
+            name: name.to_string(),
+            filename: Some(filename.to_string()),
+            content_type: Some(parse_content_type(content_type)?),
+            content: Source::Bytes(Bytes::from(contents.to_vec())),
+        });
+        Ok(this)
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn parse_content_type(content_type: Option<String>) -> Result<String, SilqError> {
+    let content_type = content_type.unwrap_or_else(|| DEFAULT_FILE_CONTENT_TYPE.to_string());
+    if content_type.contains(['\r', '\n']) {
+        Err(SilqError::new(format!(
+            "Invalid content type: {content_type:?}"
+        )))?
+    }
+    Ok(content_type)
+}
+
+/// Escape quoted parameters the way browsers do (HTML's multipart/form-data encoding algorithm).
+fn escape(value: &str) -> String {
+    value
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
@@ -0,0 +1,268 @@
+//! OAuth 2.0 access tokens (RFC 6749) obtained with the client credentials or refresh token
+//! grants, cached until shortly before they expire.
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use http::{
+    header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
+    HeaderMap, HeaderValue, StatusCode, Uri,
+};
+use serde_json::Value;
+use tokio::sync::Mutex;
+
+use crate::{error::SilqError, redirect};
+
+/// Longest time before expiry a token is renewed, to not send it as it expires.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+#[derive(Clone)]
+enum Grant {
+    ClientCredentials,
+    RefreshToken,
+}
+
+struct Token {
+    authorization: HeaderValue,
+    renew_at: Option<Instant>,
+}
+
+struct State {
+    token: Option<Token>,
+    /// Current refresh token, which the token endpoint may rotate.
+    refresh_token: Option<String>,
+}
+
+/// Access token source of a client. Clones share the cached token, so that clients built from the
+/// same builder and their clones fetch it once.
+#[derive(Clone)]
+pub struct OAuth2 {
+    token_uri: Uri,
+    client_id: String,
+    client_secret: Option<String>,
+    scope: Option<String>,
+    grant: Grant,
+    /// Origins the tokens are sent to, the token endpoint's one by default.
+    origins: Vec<Uri>,
+    state: Arc<Mutex<State>>,
+}
+
+impl OAuth2 {
+    pub fn client_credentials(
+        token_uri: &str,
+        client_id: &str,
+        client_secret: &str,
+        scopes: Option<Vec<String>>,
+    ) -> Result<Self, SilqError> {
+        Self::new(
+            token_uri,
+            client_id,
+            Some(client_secret),
+            scopes,
+            Grant::ClientCredentials,
+            None,
+        )
+    }
+
+    pub fn refresh_token(
+        token_uri: &str,
+        refresh_token: &str,
+        client_id: &str,
+        client_secret: Option<&str>,
+        scopes: Option<Vec<String>>,
+    ) -> Result<Self, SilqError> {
+        Self::new(
+            token_uri,
+            client_id,
+            client_secret,
+            scopes,
+            Grant::RefreshToken,
+            Some(refresh_token.to_string()),
+        )
+    }
+
+    fn new(
+        token_uri: &str,
+        client_id: &str,
+        client_secret: Option<&str>,
+        scopes: Option<Vec<String>>,
+        grant: Grant,
+        refresh_token: Option<String>,
+    ) -> Result<Self, SilqError> {
+        let token_uri = token_uri
+            .parse::<Uri>()
+            .map_err(|err| SilqError::from("Unable to parse token endpoint URI", &err))?;
+        Ok(Self {
+            origins: vec![token_uri.clone()],
+            token_uri,
+            client_id: client_id.to_string(),
+            client_secret: client_secret.map(str::to_string),
+            scope: scopes.map(|scopes| scopes.join(" ")),
+            grant,
+            state: Arc::new(Mutex::new(State {
+                token: None,
+                refresh_token,
+            })),
+        })
+    }
+
+    /// Send the tokens to the origins of the given URIs rather than the token endpoint's one.
+    pub fn with_origins(mut self, origins: Vec<Uri>) -> Self {
+        self.origins = origins;
+        self
+    }
+
+    /// Whether the tokens are sent to the URI's origin.
+    pub fn covers(&self, uri: &Uri) -> bool {
+        let origin = redirect::origin(uri);
+        self.origins
+            .iter()
+            .any(|audience| redirect::origin(audience) == origin)
+    }
+
+    /// `Authorization` header with a valid access token, fetched with `fetch` unless cached.
+    /// `rejected` is the header a server just answered `401` to, whose token is fetched again
+    /// unless another request already did.
+    ///
+    /// `fetch` sends a POST request to the given URI with the given headers and body, and returns
+    /// the response's status and body.
+    pub async fn authorization<F>(
+        &self,
+        rejected: Option<&HeaderValue>,
+        fetch: impl FnOnce(Uri, HeaderMap, Vec<u8>) -> F,
+    ) -> Result<HeaderValue, SilqError>
+    where
+        F: Future<Output = Result<(StatusCode, Vec<u8>), SilqError>>,
+    {
+        // Held while fetching, so that concurrent requests wait for the same token
+        let mut state = self.state.lock().await;
+        if let Some(token) = &state.token {
+            let expired = token.renew_at.is_some_and(|at| Instant::now() >= at);
+            if !expired && Some(&token.authorization) != rejected {
+                return Ok(token.authorization.clone());
+            }
+        }
+        state.token = None;
+
+        let (headers, body) = self.token_request(&state)?;
+        let requested_at = Instant::now();
+        let (status, body) = fetch(self.token_uri.clone(), headers, body).await?;
+        let token = self.parse_token(status, &body, requested_at, &mut state)?;
+        let authorization = token.authorization.clone();
+        state.token = Some(token);
+        Ok(authorization)
+    }
+
+    /// Headers and form body of the token request (RFC 6749 sections 4.4.2 and 6).
+    fn token_request(&self, state: &State) -> Result<(HeaderMap, Vec<u8>), SilqError> {
+        let mut form = vec![];
+        match self.grant {
+            Grant::ClientCredentials => form.push(("grant_type", "client_credentials")),
+            Grant::RefreshToken => {
+                form.push(("grant_type", "refresh_token"));
+                if let Some(refresh_token) = &state.refresh_token {
+                    form.push(("refresh_token", refresh_token.as_str()));
+                }
+            }
+        }
+        if let Some(scope) = &self.scope {
+            form.push(("scope", scope.as_str()));
+        }
+
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-www-form-urlencoded"),
+        );
+        match &self.client_secret {
+            // Confidential clients authenticate with HTTP Basic, their credentials form-encoded
+            Some(secret) => {
+                let credentials =
+                    format!("{}:{}", form_encode(&self.client_id), form_encode(secret));
+                let authorization = format!("Basic {}", STANDARD.encode(credentials));
+                headers.insert(
+                    AUTHORIZATION,
+                    HeaderValue::try_from(authorization).map_err(|err| {
+                        SilqError::from("Unable to encode client credentials", &err)
+                    })?,
+                );
+            }
+            None => form.push(("client_id", self.client_id.as_str())),
+        }
+
+        let body = serde_urlencoded::to_string(form)
+            .map_err(|err| SilqError::from("Unable to encode token request", &err))?;
+        Ok((headers, body.into_bytes()))
+    }
+
+    /// Parse a token response (RFC 6749 sections 5.1 and 5.2).
+    fn parse_token(
+        &self,
+        status: StatusCode,
+        body: &[u8],
+        requested_at: Instant,
+        state: &mut State,
+    ) -> Result<Token, SilqError> {
+        let json = serde_json::from_slice::<Value>(body).ok();
+        let field = |name: &str| json.as_ref()?.get(name);
+        if !status.is_success() {
+            let reason = match (field("error"), field("error_description")) {
+                (Some(Value::String(error)), Some(Value::String(description))) => {
+                    format!("{error}: {description}")
+                }
+                (Some(Value::String(error)), _) => error.clone(),
+                _ => status.to_string(),
+            };
+            Err(SilqError::new(format!(
+                "Token endpoint refused the request: {reason}"
+            )))?
+        }
+        if json.is_none() {
+            Err(SilqError::new(
+                "Invalid token response: expected a JSON object".to_string(),
+            ))?
+        }
+
+        let Some(Value::String(access_token)) = field("access_token") else {
+            return Err(SilqError::new(
+                "Invalid token response: missing access_token".to_string(),
+            ));
+        };
+        match field("token_type") {
+            Some(Value::String(token_type)) if token_type.eq_ignore_ascii_case("bearer") => {}
+            Some(token_type) => Err(SilqError::new(format!(
+                "Unsupported token type: {token_type}"
+            )))?,
+            None => Err(SilqError::new(
+                "Invalid token response: missing token_type".to_string(),
+            ))?,
+        }
+        // Some servers send the lifetime as a string
+        let expires_in = match field("expires_in") {
+            Some(Value::Number(seconds)) => seconds.as_u64(),
+            Some(Value::String(seconds)) => seconds.parse().ok(),
+            _ => None,
+        };
+        if let Some(Value::String(refresh_token)) = field("refresh_token") {
+            state.refresh_token = Some(refresh_token.clone());
+        }
+
+        let renew_at = expires_in.and_then(|seconds| {
+            let lifetime = Duration::from_secs(seconds);
+            requested_at.checked_add(lifetime - EXPIRY_MARGIN.min(lifetime / 2))
+        });
+        let authorization = HeaderValue::try_from(format!("Bearer {access_token}"))
+            .map_err(|err| SilqError::from("Invalid access token", &err))?;
+        Ok(Token {
+            authorization,
+            renew_at,
+        })
+    }
+}
+
+/// `application/x-www-form-urlencoded` encoding of a value, spaces becoming `+`.
+fn form_encode(value: &str) -> String {
+    urlencoding::encode(value).replace("%20", "+")
+}
@@ -0,0 +1,469 @@
+//! Request's payload, kept in memory or streamed from its sources while the request is sent.
+use std::future::Future;
+use std::io;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use ext_php_rs::{flags::DataType, types::Zval};
+use hyper::body::{Body, Bytes, Frame, SizeHint};
+use tokio::{fs::File, io::AsyncReadExt, sync::mpsc};
+
+use crate::{
+    callable::Callable,
+    encoding::{Compression, Encoder},
+    error::SilqError,
+};
+
+/// Size of the chunks read from streamed sources.
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Chunks read ahead of the connection.
+const CHANNEL_CAPACITY: usize = 4;
+/// Wait between two reads of a PHP stream having no data available yet, e.g. a pipe or socket.
+/// PHP streams can't notify when they become readable, so they're polled.
+const STREAM_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Part of a payload, sent in order.
+#[derive(Clone)]
+pub enum Source {
+    Bytes(Bytes),
+    /// File read from disk as it's sent.
+    File(PathBuf),
+    /// PHP stream resource, read with `fread()` from its current position.
+    Stream {
+        stream: PhpValue,
+        length: Option<u64>,
+    },
+    /// PHP `Generator` yielding the chunks.
+    Generator {
+        generator: PhpValue,
+        length: Option<u64>,
+    },
+    /// PHP callable returning the next chunk, or null or an empty string at the end.
+    Callable {
+        callable: Callable,
+        length: Option<u64>,
+    },
+}
+
+impl Source {
+    /// PHP stream resource of `length` bytes, unknown if `None`. The stream is switched to
+    /// non-blocking mode, as it's read on PHP's thread which drives all the requests.
+    pub fn stream(stream: &Zval, length: Option<u64>) -> Result<Self, SilqError> {
+        if stream.get_type() != DataType::Resource {
+            Err(SilqError::new(
+                "Body stream must be a stream resource".to_string(),
+            ))?
+        }
+        let non_blocking = Callable::function("stream_set_blocking")?
+            .call("Body stream", vec![stream, &false])?
+            .bool();
+        if non_blocking != Some(true) {
+            Err(SilqError::new(
+                "Body stream must support non-blocking mode".to_string(),
+            ))?
+        }
+        Ok(Self::Stream {
+            stream: PhpValue(stream.shallow_clone()),
+            length,
+        })
+    }
+
+    /// PHP `Generator` or callable producing `length` bytes, unknown if `None`.
+    pub fn generator(generator: &Zval, length: Option<u64>) -> Result<Self, SilqError> {
+        let is_generator = generator
+            .object()
+            .and_then(|object| object.get_class_name().ok())
+            .is_some_and(|name| name == "Generator");
+        if is_generator {
+            return Ok(Self::Generator {
+                generator: PhpValue(generator.shallow_clone()),
+                length,
+            });
+        }
+        if !generator.is_callable() {
+            Err(SilqError::new(
+                "Body generator must be a Generator or a callable".to_string(),
+            ))?
+        }
+        Ok(Self::Callable {
+            callable: Callable::new(generator, "Body generator")?,
+            length,
+        })
+    }
+
+    /// Whether the source can be read again, e.g. to retry the request.
+    fn is_replayable(&self) -> bool {
+        matches!(self, Self::Bytes(_) | Self::File(_))
+    }
+
+    async fn length(&self) -> Result<Option<u64>, SilqError> {
+        match self {
+            Self::Bytes(bytes) => Ok(Some(bytes.len() as u64)),
+            Self::File(path) => Ok(Some(
+                tokio::fs::metadata(path)
+                    .await
+                    .map_err(|err| {
+                        SilqError::from(&format!("Unable to read file {}", path.display()), &err)
+                    })?
+                    .len(),
+            )),
+            Self::Stream { length, .. }
+            | Self::Generator { length, .. }
+            | Self::Callable { length, .. } => Ok(*length),
+        }
+    }
+
+    async fn reader(&self) -> Result<Reader, SilqError> {
+        Ok(match self {
+            Self::Bytes(bytes) => Reader::Bytes(Some(bytes.clone())),
+            Self::File(path) => Reader::File(File::open(path).await.map_err(|err| {
+                SilqError::from(&format!("Unable to read file {}", path.display()), &err)
+            })?),
+            Self::Stream { stream, .. } => Reader::Stream {
+                stream: stream.clone(),
+                fread: Callable::function("fread")?,
+                feof: Callable::function("feof")?,
+            },
+            Self::Generator { generator, .. } => Reader::Generator {
+                valid: Callable::method(&generator.0, "valid")?,
+                current: Callable::method(&generator.0, "current")?,
+                next: Callable::method(&generator.0, "next")?,
+                started: false,
+            },
+            Self::Callable { callable, .. } => Reader::Callable(callable.clone()),
+        })
+    }
+}
+
+/// PHP value shared with the script, only usable from PHP's thread.
+pub struct PhpValue(Zval);
+
+impl Clone for PhpValue {
+    fn clone(&self) -> Self {
+        Self(self.0.shallow_clone())
+    }
+}
+
+/// Reads the chunks of a source, PHP ones on PHP's thread.
+enum Reader {
+    Bytes(Option<Bytes>),
+    File(File),
+    Stream {
+        stream: PhpValue,
+        fread: Callable,
+        feof: Callable,
+    },
+    Generator {
+        valid: Callable,
+        current: Callable,
+        next: Callable,
+        started: bool,
+    },
+    Callable(Callable),
+}
+
+impl Reader {
+    /// Next chunk, reading at most `max` bytes from files and streams. `None` at the end of the
+    /// source.
+    async fn next(&mut self, max: u64) -> Result<Option<Bytes>, SilqError> {
+        match self {
+            Self::Bytes(bytes) => Ok(bytes.take()),
+            Self::File(file) => {
+                let mut chunk = vec![0; max.min(CHUNK_SIZE as u64) as usize];
+                let read = file
+                    .read(&mut chunk)
+                    .await
+                    .map_err(|err| SilqError::from("Unable to read file", &err))?;
+                chunk.truncate(read);
+                Ok((read > 0).then(|| Bytes::from(chunk)))
+            }
+            Self::Stream {
+                stream,
+                fread,
+                feof,
+            } => loop {
+                let size = max.min(CHUNK_SIZE as u64) as i64;
+                let chunk = fread
+                    .call("Body stream", vec![&stream.0, &size])?
+                    .binary::<u8>()
+                    .ok_or_else(|| SilqError::new("Unable to read body stream".to_string()))?;
+                if !chunk.is_empty() {
+                    return Ok(Some(Bytes::from(chunk)));
+                }
+                if feof.call("Body stream", vec![&stream.0])?.bool() != Some(false) {
+                    return Ok(None);
+                }
+                tokio::time::sleep(STREAM_POLL_INTERVAL).await;
+            },
+            Self::Generator {
+                valid,
+                current,
+                next,
+                started,
+            } => {
+                if *started {
+                    next.call("Body generator", vec![])?;
+                }
+                *started = true;
+                if valid.call("Body generator", vec![])?.bool() != Some(true) {
+                    return Ok(None);
+                }
+                into_chunk(current.call("Body generator", vec![])?).map(Some)
+            }
+            Self::Callable(callable) => {
+                let chunk = callable.call("Body generator", vec![])?;
+                if chunk.is_null() {
+                    return Ok(None);
+                }
+                let chunk = into_chunk(chunk)?;
+                Ok((!chunk.is_empty()).then_some(chunk))
+            }
+        }
+    }
+}
+
+fn into_chunk(value: Zval) -> Result<Bytes, SilqError> {
+    value
+        .binary::<u8>()
+        .map(Bytes::from)
+        .ok_or_else(|| SilqError::new("Body generator must produce strings".to_string()))
+}
+
+#[derive(Clone, Default)]
+pub struct Payload {
+    sources: Vec<Source>,
+    /// Compression applied while streaming the sources. In-memory payloads are compressed once
+    /// instead, see `compress()`.
+    compression: Option<Compression>,
+}
+
+impl Payload {
+    pub fn new(sources: Vec<Source>) -> Self {
+        Self {
+            sources,
+            compression: None,
+        }
+    }
+
+    pub fn bytes(bytes: impl Into<Bytes>) -> Self {
+        Self::new(vec![Source::Bytes(bytes.into())])
+    }
+
+    /// Whether the payload can be sent again, e.g. when retrying the request.
+    pub fn is_replayable(&self) -> bool {
+        self.sources.iter().all(Source::is_replayable)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources
+            .iter()
+            .all(|source| matches!(source, Source::Bytes(bytes) if bytes.is_empty()))
+    }
+
+    /// Compress the payload. In-memory payloads are compressed at once, so that the same bytes
+    /// are signed and sent again on retries and redirects, streamed ones as they're sent.
+    pub fn compress(&mut self, compression: Compression) -> Result<(), SilqError> {
+        // Empty payloads are sent as-is, without a body to decode
+        if self.is_empty() {
+            return Ok(());
+        }
+        match self.to_bytes() {
+            Some(bytes) => {
+                let compressed = Bytes::from(compression.compress(&bytes)?);
+                self.sources = vec![Source::Bytes(compressed)];
+            }
+            None => self.compression = Some(compression),
+        }
+        Ok(())
+    }
+
+    /// Body as sent, if the payload is fully in memory.
+    pub fn to_bytes(&self) -> Option<Bytes> {
+        if let [Source::Bytes(bytes)] = self.sources.as_slice() {
+            return Some(bytes.clone());
+        }
+        let mut buffer = vec![];
+        for source in &self.sources {
+            match source {
+                Source::Bytes(bytes) => buffer.extend_from_slice(bytes),
+                _ => return None,
+            }
+        }
+        Some(Bytes::from(buffer))
+    }
+
+    /// Body sending the payload. Streamed payloads also return the feeder writing their chunks to
+    /// the body, which must run while the request is sent.
+    pub async fn body(&self) -> Result<(RequestBody, Option<Feeder>), SilqError> {
+        if let Some(bytes) = self.to_bytes() {
+            return Ok((
+                RequestBody::Full(Some(bytes).filter(|b| !b.is_empty())),
+                None,
+            ));
+        }
+
+        let mut sources = Vec::with_capacity(self.sources.len());
+        let mut length = Some(0);
+        for source in &self.sources {
+            let source_length = source.length().await?;
+            length = length
+                .zip(source_length)
+                .map(|(total, length)| total + length);
+            sources.push((source.clone(), source_length));
+        }
+
+        let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+        let encoder = self.compression.map(|c| c.encoder()).transpose()?;
+        // Compressed length is only known once compressed
+        let length = length.filter(|_| encoder.is_none());
+        Ok((
+            RequestBody::Channel { receiver, length },
+            Some(Feeder {
+                sources,
+                sender,
+                encoder,
+            }),
+        ))
+    }
+}
+
+/// Body of requests, either complete or receiving its chunks from a `Feeder`.
+pub enum RequestBody {
+    Full(Option<Bytes>),
+    Channel {
+        receiver: mpsc::Receiver<io::Result<Bytes>>,
+        length: Option<u64>,
+    },
+}
+
+impl Body for RequestBody {
+    type Data = Bytes;
+    type Error = io::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        match self.get_mut() {
+            Self::Full(bytes) => Poll::Ready(bytes.take().map(|bytes| Ok(Frame::data(bytes)))),
+            Self::Channel { receiver, .. } => receiver
+                .poll_recv(cx)
+                .map(|chunk| chunk.map(|chunk| chunk.map(Frame::data))),
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        matches!(self, Self::Full(None))
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        match self {
+            Self::Full(bytes) => {
+                SizeHint::with_exact(bytes.as_ref().map_or(0, |bytes| bytes.len() as u64))
+            }
+            Self::Channel {
+                length: Some(length),
+                ..
+            } => SizeHint::with_exact(*length),
+            Self::Channel { length: None, .. } => SizeHint::default(),
+        }
+    }
+}
+
+/// Reads the sources of a streamed payload into its body.
+pub struct Feeder {
+    sources: Vec<(Source, Option<u64>)>,
+    sender: mpsc::Sender<io::Result<Bytes>>,
+    encoder: Option<Encoder>,
+}
+
+impl Feeder {
+    /// Feed the body until the end of the payload, or until the body is dropped, e.g. because the
+    /// response came early. Errors are also passed to the body so that the request is aborted.
+    pub async fn run(mut self) -> Result<(), SilqError> {
+        match self.feed().await {
+            Err(err) => {
+                let _ = self
+                    .sender
+                    .send(Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        err.description.clone(),
+                    )))
+                    .await;
+                Err(err)
+            }
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn feed(&mut self) -> Result<(), SilqError> {
+        for (source, length) in std::mem::take(&mut self.sources) {
+            let mut reader = source.reader().await?;
+            let mut read = 0;
+            // Files and streams are read up to their length, in case they hold more
+            while length.map_or(true, |length| read < length) {
+                let max = length.map_or(u64::MAX, |length| length - read);
+                let Some(chunk) = reader.next(max).await? else {
+                    break;
+                };
+                read += chunk.len() as u64;
+                // The length was announced already, it can't change anymore
+                if let Some(length) = length.filter(|length| read > *length) {
+                    Err(SilqError::new(format!(
+                        "Body is longer than its announced length of {length} bytes"
+                    )))?
+                }
+                if !self.send(chunk).await? {
+                    return Ok(());
+                }
+            }
+            if let Some(length) = length.filter(|length| read < *length) {
+                Err(SilqError::new(format!(
+                    "Body is shorter than its announced length of {length} bytes"
+                )))?
+            }
+        }
+        if let Some(encoder) = &mut self.encoder {
+            let rest = encoder.finish()?;
+            if !rest.is_empty() {
+                self.sender.send(Ok(Bytes::from(rest))).await.ok();
+            }
+        }
+        Ok(())
+    }
+
+    /// Send the chunk, compressed if needed. Returns whether the body still accepts chunks.
+    async fn send(&mut self, chunk: Bytes) -> Result<bool, SilqError> {
+        let chunk = match &mut self.encoder {
+            Some(encoder) => Bytes::from(encoder.encode(&chunk)?),
+            None => chunk,
+        };
+        if chunk.is_empty() {
+            return Ok(true);
+        }
+        Ok(self.sender.send(Ok(chunk)).await.is_ok())
+    }
+}
+
+/// Run `send` while the feeder, if any, feeds the request's body. Stops feeding as soon as `send`
+/// completes.
+pub async fn feeding<T>(
+    feeder: Option<Feeder>,
+    send: impl Future<Output = Result<T, SilqError>>,
+) -> Result<T, SilqError> {
+    let Some(feeder) = feeder else {
+        return send.await;
+    };
+    tokio::pin!(send);
+    tokio::select! {
+        // Feeding errors explain better why the request failed
+        biased;
+        fed = feeder.run() => {
+            fed?;
+            send.await
+        }
+        outcome = &mut send => outcome,
+    }
+}
@@ -0,0 +1,125 @@
+//! Keep-alive connection pool shared by an `HttpClient` and all its clones.
+//!
+//! Connections are keyed by scheme, address and the proxy or socket they go through. The TLS
+//! configuration is fixed for a given client, so it is implicitly part of the key: two clients
+//! never share a pool.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use http::uri::Scheme;
+
+use crate::connection::Sender;
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct PoolKey {
+    scheme: Scheme,
+    address: String,
+    via: Option<String>,
+}
+
+impl PoolKey {
+    pub fn new(scheme: &Scheme, address: &str, via: Option<String>) -> Self {
+        Self {
+            scheme: scheme.clone(),
+            address: address.to_string(),
+            via,
+        }
+    }
+}
+
+struct Idle {
+    sender: Sender,
+    idle_at: Instant,
+}
+
+impl Idle {
+    fn is_reusable(&self, now: Instant, idle_timeout: Option<Duration>) -> bool {
+        let expired =
+            idle_timeout.is_some_and(|timeout| now.duration_since(self.idle_at) > timeout);
+        !expired && !self.sender.is_closed() && self.sender.is_ready()
+    }
+}
+
+#[derive(Clone)]
+pub struct Pool {
+    idle: Arc<Mutex<HashMap<PoolKey, Vec<Idle>>>>,
+    idle_timeout: Option<Duration>,
+    max_idle_per_host: usize,
+}
+
+impl Pool {
+    pub fn new(idle_timeout: Option<Duration>, max_idle_per_host: usize) -> Self {
+        Self {
+            idle: Arc::new(Mutex::new(HashMap::new())),
+            idle_timeout,
+            max_idle_per_host,
+        }
+    }
+
+    /// Take an idle connection for the given key, discarding expired and closed ones on the way.
+    /// Shareable connections stay in the pool while being used.
+    pub fn checkout(&self, key: &PoolKey) -> Option<Sender> {
+        let mut idle = self.idle.lock().unwrap_or_else(PoisonError::into_inner);
+        let connections = idle.get_mut(key)?;
+        let now = Instant::now();
+        let mut found = None;
+        // Most recently used connections are at the end and the least likely to be closed.
+        while let Some(mut connection) = connections.pop() {
+            if connection.is_reusable(now, self.idle_timeout) {
+                if let Some(shared) = connection.sender.share() {
+                    connection.idle_at = now;
+                    connections.push(connection);
+                    found = Some(shared);
+                } else {
+                    found = Some(connection.sender);
+                }
+                break;
+            }
+        }
+        if connections.is_empty() {
+            idle.remove(key);
+        }
+        found
+    }
+
+    /// Give back a connection once it becomes available again, i.e. when the response's body has
+    /// been fully consumed. Connections closed in-between are dropped.
+    pub fn checkin(&self, key: PoolKey, mut sender: Sender) {
+        if self.max_idle_per_host == 0 {
+            return;
+        }
+        if sender.is_multiplexed() {
+            // Shareable connections are available right away
+            self.put(key, sender);
+            return;
+        }
+        let pool = self.clone();
+        tokio::spawn(async move {
+            if sender.ready().await.is_ok() {
+                pool.put(key, sender);
+            }
+        });
+    }
+
+    fn put(&self, key: PoolKey, sender: Sender) {
+        let mut idle = self.idle.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        let connections = idle.entry(key).or_default();
+        connections.retain(|connection| connection.is_reusable(now, self.idle_timeout));
+        if sender.is_multiplexed()
+            && connections
+                .iter()
+                .any(|connection| connection.sender.is_multiplexed())
+        {
+            // A shareable connection to this host is already pooled, no need for another one.
+            return;
+        }
+        if connections.len() < self.max_idle_per_host {
+            connections.push(Idle {
+                sender,
+                idle_at: now,
+            });
+        }
+    }
+}
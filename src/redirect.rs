@@ -0,0 +1,219 @@
+//! Redirect following: which redirects to follow, where they lead and how the request changes.
+use ext_php_rs::types::Zval;
+use http::{
+    header::{AUTHORIZATION, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, LOCATION},
+    uri::Scheme,
+    HeaderMap, Method, StatusCode, Uri,
+};
+
+use crate::{callable::Callable, error::SilqError, payload::Payload};
+
+#[derive(Clone)]
+pub enum RedirectPolicy {
+    /// Return redirect responses as-is.
+    None,
+    /// Follow up to the given number of redirects, failing past it.
+    Limit(usize),
+    /// Ask a PHP callable whether to follow each redirect.
+    Custom(Callable),
+}
+
+impl RedirectPolicy {
+    /// Parse a number of redirects to follow, or a callable deciding whether to follow them.
+    pub fn parse(policy: &Zval) -> Result<Self, SilqError> {
+        match policy.long() {
+            Some(0) => Ok(Self::None),
+            Some(max) => {
+                Ok(Self::Limit(max.try_into().map_err(|err| {
+                    SilqError::from("Invalid number of redirects", &err)
+                })?))
+            }
+            None => Ok(Self::Custom(Callable::new(policy, "Redirect policy")?)),
+        }
+    }
+
+    /// Whether to follow the redirect to `next`, after those in `chain` were followed.
+    pub fn follows(
+        &self,
+        status: StatusCode,
+        next: &Uri,
+        chain: &[Uri],
+    ) -> Result<bool, SilqError> {
+        match self {
+            Self::None => Ok(false),
+            Self::Limit(max) if chain.len() >= *max => Err(SilqError::new(format!(
+                "Too many redirects, stopped after {max}"
+            ))),
+            Self::Limit(_) => Ok(true),
+            Self::Custom(callable) => {
+                let chain = chain.iter().map(Uri::to_string).collect::<Vec<_>>();
+                callable
+                    .call(
+                        "Redirect policy",
+                        vec![&next.to_string(), &i64::from(status.as_u16()), &chain],
+                    )?
+                    .bool()
+                    .ok_or_else(|| SilqError::new("Redirect policy must return a bool".to_string()))
+            }
+        }
+    }
+}
+
+/// Target of a redirect response, resolved against the requested URI.
+pub fn location(
+    status: StatusCode,
+    headers: &HeaderMap,
+    uri: &Uri,
+) -> Option<Result<Uri, SilqError>> {
+    match status {
+        StatusCode::MOVED_PERMANENTLY
+        | StatusCode::FOUND
+        | StatusCode::SEE_OTHER
+        | StatusCode::TEMPORARY_REDIRECT
+        | StatusCode::PERMANENT_REDIRECT => {}
+        _ => return None,
+    }
+    let location = headers.get(LOCATION)?;
+    Some(resolve(uri, location.as_bytes()))
+}
+
+/// Whether following the redirect sends the request's body again: 301 and 302 turn POST into GET,
+/// 303 turns all but HEAD into GET, dropping the body.
+pub fn keeps_body(status: StatusCode, method: &Method) -> bool {
+    match status {
+        StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND => *method != Method::POST,
+        StatusCode::SEE_OTHER => *method == Method::HEAD || *method == Method::GET,
+        _ => true,
+    }
+}
+
+/// Adapt the request to follow a redirect from `from` to `to`, see `keeps_body`. Credentials are
+/// only sent to the origin they were given for.
+pub fn rewrite(
+    status: StatusCode,
+    method: &mut Method,
+    headers: &mut HeaderMap,
+    payload: &mut Payload,
+    from: &Uri,
+    to: &Uri,
+) {
+    if !keeps_body(status, method) {
+        *method = Method::GET;
+        *payload = Payload::default();
+        for header in [CONTENT_TYPE, CONTENT_LENGTH, CONTENT_ENCODING] {
+            headers.remove(header);
+        }
+    }
+
+    if origin(from) != origin(to) {
+        headers.remove(AUTHORIZATION);
+        headers.remove(COOKIE);
+    }
+}
+
+/// Whether following the redirect would downgrade from HTTPS to unsecure HTTP.
+pub fn is_downgrade(from: &Uri, to: &Uri) -> bool {
+    from.scheme() == Some(&Scheme::HTTPS) && to.scheme() == Some(&Scheme::HTTP)
+}
+
+/// Scheme, host and port of the URI, defaulting the port to the scheme's one.
+pub fn origin(uri: &Uri) -> (Option<&str>, Option<String>, Option<u16>) {
+    let port = uri.port_u16().or(match uri.scheme_str() {
+        Some("https") => Some(443),
+        Some("http") => Some(80),
+        _ => None,
+    });
+    (uri.scheme_str(), uri.host().map(str::to_lowercase), port)
+}
+
+/// Resolve a URI reference against `base` (RFC 3986 section 5.2), dropping its fragment. Raw
+/// bytes of the reference, e.g. UTF-8, are percent-encoded.
+pub fn resolve(base: &Uri, reference: &[u8]) -> Result<Uri, SilqError> {
+    let reference = encode(reference.trim_ascii());
+    let reference = match reference.split_once('#') {
+        Some((reference, _)) => reference,
+        None => reference.as_str(),
+    };
+
+    let scheme = base.scheme_str().unwrap_or("http");
+    let authority = base.authority().map(|a| a.as_str()).unwrap_or_default();
+    let resolved = if has_scheme(reference) {
+        reference.to_string()
+    } else if reference.starts_with("//") {
+        format!("{scheme}:{reference}")
+    } else {
+        let (path, query) = match reference.find('?') {
+            Some(index) => reference.split_at(index),
+            None => (reference, ""),
+        };
+        let (path, query) = if path.is_empty() {
+            let query = match query {
+                "" => base
+                    .query()
+                    .map(|query| format!("?{query}"))
+                    .unwrap_or_default(),
+                query => query.to_string(),
+            };
+            (base.path().to_string(), query)
+        } else if path.starts_with('/') {
+            (remove_dot_segments(path), query.to_string())
+        } else {
+            let base_path = base.path();
+            let directory = &base_path[..base_path.rfind('/').map_or(0, |index| index + 1)];
+            (
+                remove_dot_segments(&format!("{directory}{path}")),
+                query.to_string(),
+            )
+        };
+        format!("{scheme}://{authority}{path}{query}")
+    };
+
+    resolved
+        .parse::<Uri>()
+        .map_err(|err| SilqError::from("Invalid redirect location", &err))
+}
+
+fn has_scheme(reference: &str) -> bool {
+    match reference.split_once(':') {
+        Some((scheme, _)) => {
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        }
+        None => false,
+    }
+}
+
+fn remove_dot_segments(path: &str) -> String {
+    let mut output = vec![];
+    let segments = path.strip_prefix('/').unwrap_or(path).split('/');
+    let mut last = "";
+    for segment in segments {
+        match segment {
+            "." => {}
+            ".." => {
+                output.pop();
+            }
+            segment => output.push(segment),
+        }
+        last = segment;
+    }
+    if last == "." || last == ".." {
+        output.push("");
+    }
+    format!("/{}", output.join("/"))
+}
+
+/// Percent-encode what servers commonly leave raw in `Location` headers, like spaces and UTF-8.
+fn encode(reference: &[u8]) -> String {
+    let mut encoded = String::with_capacity(reference.len());
+    for &byte in reference {
+        if byte.is_ascii_graphic() {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
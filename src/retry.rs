@@ -0,0 +1,207 @@
+//! Retry policy: which failed attempts are worth repeating, and how long to wait before.
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, SystemTime};
+
+use ext_php_rs::{prelude::*, types::ZendClassObject};
+use http::{header::RETRY_AFTER, HeaderMap, Method, StatusCode};
+use hyper::body::Incoming;
+
+use crate::{
+    error::{Failure, SilqError, Timeout},
+    parse_duration,
+};
+
+const DEFAULT_BASE_DELAY: Duration = Duration::from_millis(100);
+const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(10);
+const DEFAULT_STATUSES: [u16; 4] = [429, 502, 503, 504];
+
+/// Retry policy, given to `HttpClientBuilder::withRetryPolicy` or `RequestBuilder::withRetryPolicy`.
+///
+/// Attempts are retried on connection failures, timeouts before the response and the 429, 502,
+/// 503 and 504 statuses by default, for idempotent methods only. Delays grow exponentially with
+/// full jitter, unless the server asks for a delay through the `Retry-After` header.
+#[php_class(name = "Silq\\RetryPolicy")]
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    statuses: Vec<u16>,
+    connect_errors: bool,
+    reset_errors: bool,
+    timeout_errors: bool,
+    all_methods: bool,
+    retry_after: bool,
+}
+
+impl RetryPolicy {
+    /// Policy making a single attempt.
+    pub fn none() -> Self {
+        Self::new(1)
+    }
+
+    /// Delay before the next attempt, or `None` if the outcome of the given attempt is final.
+    pub fn retry_delay(
+        &self,
+        method: &Method,
+        attempt: u32,
+        outcome: &Result<hyper::Response<Incoming>, SilqError>,
+    ) -> Option<Duration> {
+        if attempt >= self.max_attempts || !(self.all_methods || method.is_idempotent()) {
+            return None;
+        }
+
+        let backoff = self.backoff(attempt);
+        match outcome {
+            Ok(res) if self.statuses.contains(&res.status().as_u16()) => {
+                match retry_after(res.status(), res.headers()).filter(|_| self.retry_after) {
+                    // Waiting longer than allowed is pointless, let the caller decide
+                    Some(delay) if delay > self.max_delay => None,
+                    Some(delay) => Some(delay),
+                    None => Some(backoff),
+                }
+            }
+            Ok(_) => None,
+            Err(err) => {
+                let retryable = match (err.failure, err.timeout) {
+                    (Some(Failure::Connect), _) => self.connect_errors,
+                    (Some(Failure::Reset | Failure::Unsent | Failure::Stale), _) => {
+                        self.reset_errors
+                    }
+                    (_, Some(Timeout::Connect | Timeout::TlsHandshake | Timeout::Response)) => {
+                        self.timeout_errors
+                    }
+                    _ => false,
+                };
+                retryable.then_some(backoff)
+            }
+        }
+    }
+
+    /// Random delay up to the exponential backoff for the given attempt.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay);
+        let random = RandomState::new().build_hasher().finish();
+        exponential.mul_f64(random as f64 / u64::MAX as f64)
+    }
+}
+
+/// Delay asked by the server, in seconds or as a date.
+fn retry_after(status: StatusCode, headers: &HeaderMap) -> Option<Duration> {
+    if status != StatusCode::TOO_MANY_REQUESTS && status != StatusCode::SERVICE_UNAVAILABLE {
+        return None;
+    }
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    match value.parse::<u64>() {
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(_) => {
+            let date = httpdate::parse_http_date(value).ok()?;
+            Some(
+                date.duration_since(SystemTime::now())
+                    .unwrap_or(Duration::ZERO),
+            )
+        }
+    }
+}
+
+#[php_impl]
+impl RetryPolicy {
+    /// @param max_attempts int Maximum number of attempts, the first one included.
+    #[constructor]
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            base_delay: DEFAULT_BASE_DELAY,
+            max_delay: DEFAULT_MAX_DELAY,
+            statuses: DEFAULT_STATUSES.to_vec(),
+            connect_errors: true,
+            reset_errors: true,
+            timeout_errors: true,
+            all_methods: false,
+            retry_after: true,
+        }
+    }
+
+    /// Set the delay before the first retry, doubled for each following one up to `max`.
+    ///
+    /// @param base float [default: 0.1]
+    /// @param max float [default: 10.0] Also bounds the delays asked through `Retry-After`.
+    /// @return RetryPolicy
+    pub fn with_backoff(
+        #[this] this: &mut ZendClassObject<Self>,
+        base: f64,
+        max: f64,
+    ) -> PhpResult<&mut ZendClassObject<Self>> {
+        this.base_delay = parse_duration(base)?;
+        this.max_delay = parse_duration(max)?;
+        Ok(this)
+    }
+
+    /// Set the response statuses to retry.
+    ///
+    /// @param statuses int[] [default: [429, 502, 503, 504]]
+    /// @return RetryPolicy
+    pub fn with_statuses(
+        #[this] this: &mut ZendClassObject<Self>,
+        statuses: Vec<u16>,
+    ) -> &mut ZendClassObject<Self> {
+        this.statuses = statuses;
+        this
+    }
+
+    /// Set the errors to retry: `connect` when the connection can't be established, `reset` when
+    /// it closes before the response, `timeout` when connecting or waiting for the response
+    /// takes too long.
+    ///
+    /// @param errors string[] [default: ["connect", "reset", "timeout"]]
+    /// @return RetryPolicy
+    pub fn with_errors<'a>(
+        #[this] this: &'a mut ZendClassObject<Self>,
+        errors: Vec<String>,
+    ) -> PhpResult<&'a mut ZendClassObject<Self>> {
+        let (mut connect, mut reset, mut timeout) = (false, false, false);
+        for error in &errors {
+            match error.as_str() {
+                "connect" => connect = true,
+                "reset" => reset = true,
+                "timeout" => timeout = true,
+                _ => Err(SilqError::new(format!(
+                    "Unknown retryable error: {error}, expected connect, reset or timeout"
+                )))?,
+            }
+        }
+        this.connect_errors = connect;
+        this.reset_errors = reset;
+        this.timeout_errors = timeout;
+        Ok(this)
+    }
+
+    /// Also retry requests with non-idempotent methods, like POST and PATCH.
+    ///
+    /// @param enable bool
+    /// @return RetryPolicy
+    pub fn with_non_idempotent_methods(
+        #[this] this: &mut ZendClassObject<Self>,
+        enable: bool,
+    ) -> &mut ZendClassObject<Self> {
+        this.all_methods = enable;
+        this
+    }
+
+    /// Wait for the delay given by the `Retry-After` header of 429 and 503 responses, rather than
+    /// the backoff.
+    ///
+    /// @param enable bool [default: true]
+    /// @return RetryPolicy
+    pub fn with_retry_after(
+        #[this] this: &mut ZendClassObject<Self>,
+        enable: bool,
+    ) -> &mut ZendClassObject<Self> {
+        this.retry_after = enable;
+        this
+    }
+}
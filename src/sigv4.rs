@@ -0,0 +1,313 @@
+//! AWS Signature Version 4: signs requests to AWS services and S3-compatible storages, either
+//! through their headers or as presigned URLs.
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ext_php_rs::prelude::*;
+use hmac::{Hmac, Mac};
+use http::{header::AUTHORIZATION, HeaderMap, HeaderValue, Method, Uri};
+use sha2::{Digest, Sha256};
+
+use crate::error::SilqError;
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+/// Payload hash of bodies that can't be hashed before being sent, only accepted by S3.
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+const DEFAULT_PRESIGNED_EXPIRATION: u64 = 3600;
+const MAX_PRESIGNED_EXPIRATION: u64 = 7 * 24 * 3600;
+/// Headers which intermediaries may change, left out of the signature.
+const UNSIGNED_HEADERS: [&str; 5] = [
+    "authorization",
+    "connection",
+    "expect",
+    "user-agent",
+    "x-amzn-trace-id",
+];
+
+/// Signer of requests with AWS Signature Version 4, given to `HttpClientBuilder::withAwsSigner`
+/// or `RequestBuilder::withAwsSigner`.
+///
+/// Requests are signed with their final headers, after redirects, and the hash of their body.
+/// Streamed bodies are signed as `UNSIGNED-PAYLOAD`, which only S3 accepts.
+#[php_class(name = "Silq\\AwsSigner")]
+#[derive(Clone)]
+pub struct AwsSigner {
+    access_key: String,
+    secret_key: String,
+    session_token: Option<String>,
+    region: String,
+    service: String,
+}
+
+impl AwsSigner {
+    /// Sign the request with the `Authorization` header, adding the `X-Amz-*` headers it covers.
+    /// `body` is the body as sent, if in memory.
+    pub fn sign(
+        &self,
+        method: &Method,
+        uri: &Uri,
+        headers: &mut HeaderMap,
+        body: Option<&[u8]>,
+    ) -> Result<(), SilqError> {
+        let (date, time) = timestamps(SystemTime::now());
+        let payload_hash = body.map_or_else(|| UNSIGNED_PAYLOAD.to_string(), sha256);
+
+        let header = |value: &str| {
+            HeaderValue::try_from(value)
+                .map_err(|err| SilqError::from("Unable to encode signature header", &err))
+        };
+        headers.insert("x-amz-date", header(&time)?);
+        headers.insert("x-amz-content-sha256", header(&payload_hash)?);
+        if let Some(session_token) = &self.session_token {
+            headers.insert("x-amz-security-token", header(session_token)?);
+        }
+
+        let (canonical_headers, signed_headers) = canonical_headers(headers);
+        let canonical_request = format!(
+            "{method}\n{}\n{}\n{canonical_headers}\n{signed_headers}\n{payload_hash}",
+            self.canonical_path(uri),
+            canonical_query(uri.query()),
+        );
+        let signature = self.signature(&date, &time, &canonical_request);
+        let authorization = format!(
+            "{ALGORITHM} Credential={}/{}, SignedHeaders={signed_headers}, Signature={signature}",
+            self.access_key,
+            self.scope(&date),
+        );
+        headers.insert(AUTHORIZATION, header(&authorization)?);
+        Ok(())
+    }
+
+    fn scope(&self, date: &str) -> String {
+        format!("{date}/{}/{}/aws4_request", self.region, self.service)
+    }
+
+    /// Path encoded once for S3, twice for the other services.
+    fn canonical_path(&self, uri: &Uri) -> String {
+        let path = match uri.path() {
+            "" => "/",
+            path => path,
+        };
+        path.split('/')
+            .map(|segment| {
+                let encoded = encode(&urlencoding::decode_binary(segment.as_bytes()));
+                match self.service.as_str() {
+                    "s3" => encoded,
+                    _ => encode(encoded.as_bytes()),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    fn signature(&self, date: &str, time: &str, canonical_request: &str) -> String {
+        let string_to_sign = format!(
+            "{ALGORITHM}\n{time}\n{}\n{}",
+            self.scope(date),
+            sha256(canonical_request.as_bytes()),
+        );
+        let key = [
+            date,
+            self.region.as_str(),
+            self.service.as_str(),
+            "aws4_request",
+        ]
+        .iter()
+        .fold(
+            format!("AWS4{}", self.secret_key).into_bytes(),
+            |key, data| hmac(&key, data),
+        );
+        hex(&hmac(&key, &string_to_sign))
+    }
+}
+
+#[php_impl]
+impl AwsSigner {
+    /// @param access_key string
+    /// @param secret_key string
+    /// @param region string e.g. "us-east-1"
+    /// @param service string e.g. "s3", "sqs" or "es"
+    /// @param session_token string|null token of temporary credentials
+    #[constructor]
+    pub fn new(
+        access_key: &str,
+        secret_key: &str,
+        region: &str,
+        service: &str,
+        session_token: Option<String>,
+    ) -> Self {
+        Self {
+            access_key: access_key.to_string(),
+            secret_key: secret_key.to_string(),
+            session_token,
+            region: region.to_string(),
+            service: service.to_string(),
+        }
+    }
+
+    /// Presigned URL granting the given request to whoever has it, e.g. to download or upload an
+    /// S3 object, until it expires. Its body isn't signed.
+    ///
+    /// @param method string
+    /// @param uri string
+    /// @param expires_in int seconds the URL is valid for, up to 7 days [default: 3600]
+    /// @return string
+    pub fn presign(&self, method: &str, uri: &str, expires_in: Option<i64>) -> PhpResult<String> {
+        let method = Method::from_bytes(method.to_uppercase().as_bytes())
+            .map_err(|err| SilqError::from("Invalid method", &err))?;
+        let uri = uri
+            .parse::<Uri>()
+            .map_err(|err| SilqError::from("Unable to parse URI", &err))?;
+        let Some(authority) = uri.authority() else {
+            return Err(SilqError::new("Unable to extract URI's authority".to_string()).into());
+        };
+        let expires_in = expires_in
+            .map_or(Some(DEFAULT_PRESIGNED_EXPIRATION), |seconds| {
+                seconds.try_into().ok()
+            })
+            .filter(|seconds| (1..=MAX_PRESIGNED_EXPIRATION).contains(seconds))
+            .ok_or_else(|| {
+                SilqError::new(
+                    "Presigned URL expiration must be between 1 second and 7 days".to_string(),
+                )
+            })?;
+
+        let (date, time) = timestamps(SystemTime::now());
+        let credential = format!("{}/{}", self.access_key, self.scope(&date));
+        let mut params = vec![
+            ("X-Amz-Algorithm", ALGORITHM.to_string()),
+            ("X-Amz-Credential", credential),
+            ("X-Amz-Date", time.clone()),
+            ("X-Amz-Expires", expires_in.to_string()),
+            ("X-Amz-SignedHeaders", "host".to_string()),
+        ];
+        if let Some(session_token) = &self.session_token {
+            params.push(("X-Amz-Security-Token", session_token.clone()));
+        }
+        let params = params
+            .iter()
+            .map(|(name, value)| format!("{name}={}", encode(value.as_bytes())))
+            .collect::<Vec<_>>()
+            .join("&");
+        let query = match uri.query().filter(|query| !query.is_empty()) {
+            Some(query) => format!("{query}&{params}"),
+            None => params,
+        };
+
+        let canonical_request = format!(
+            "{method}\n{}\n{}\nhost:{authority}\n\nhost\n{UNSIGNED_PAYLOAD}",
+            self.canonical_path(&uri),
+            canonical_query(Some(&query)),
+        );
+        let signature = self.signature(&date, &time, &canonical_request);
+        let scheme = uri.scheme_str().unwrap_or("https");
+        Ok(format!(
+            "{scheme}://{authority}{}?{query}&X-Amz-Signature={signature}",
+            uri.path()
+        ))
+    }
+}
+
+/// Sorted headers, lowercase with trimmed values, and the list of their names.
+fn canonical_headers(headers: &HeaderMap) -> (String, String) {
+    let mut names = headers
+        .keys()
+        .map(|name| name.as_str())
+        .filter(|name| !UNSIGNED_HEADERS.contains(name))
+        .collect::<Vec<_>>();
+    names.sort_unstable();
+
+    let mut canonical = String::new();
+    for name in &names {
+        let values = headers
+            .get_all(*name)
+            .iter()
+            .map(|value| {
+                let value = String::from_utf8_lossy(value.as_bytes());
+                value.split_whitespace().collect::<Vec<_>>().join(" ")
+            })
+            .collect::<Vec<_>>();
+        canonical.push_str(&format!("{name}:{}\n", values.join(",")));
+    }
+    (canonical, names.join(";"))
+}
+
+/// Query parameters encoded and sorted by name, then value.
+fn canonical_query(query: Option<&str>) -> String {
+    let mut params = query
+        .unwrap_or_default()
+        .split('&')
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let (name, value) = param.split_once('=').unwrap_or((param, ""));
+            let canonical = |part: &str| encode(&urlencoding::decode_binary(part.as_bytes()));
+            (canonical(name), canonical(value))
+        })
+        .collect::<Vec<_>>();
+    params.sort();
+    params
+        .iter()
+        .map(|(name, value)| format!("{name}={value}"))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Percent-encode all bytes but the unreserved characters, as AWS expects.
+fn encode(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                char::from(byte).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+fn sha256(data: &[u8]) -> String {
+    hex(&Sha256::digest(data))
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Date and time of the signature, e.g. `20150830` and `20150830T123600Z`.
+fn timestamps(time: SystemTime) -> (String, String) {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let (year, month, day) = civil_from_days((seconds / 86400) as i64);
+    let date = format!("{year:04}{month:02}{day:02}");
+    let time = format!(
+        "{date}T{:02}{:02}{:02}Z",
+        seconds % 86400 / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    );
+    (date, time)
+}
+
+/// Year, month and day of the given number of days since the Unix epoch.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = (if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    }) as u32;
+    (year_of_era + era * 400 + i64::from(month <= 2), month, day)
+}
@@ -0,0 +1,158 @@
+//! Client side of the SOCKS5 protocol (RFC 1928) with username/password authentication (RFC 1929).
+use std::net::{IpAddr, SocketAddr};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use crate::error::SilqError;
+
+const VERSION: u8 = 0x05;
+const NO_AUTHENTICATION: u8 = 0x00;
+const USERNAME_PASSWORD: u8 = 0x02;
+const NO_ACCEPTABLE_METHOD: u8 = 0xff;
+const USERNAME_PASSWORD_VERSION: u8 = 0x01;
+const CONNECT: u8 = 0x01;
+const ATYP_IPV4: u8 = 0x01;
+const ATYP_DOMAIN: u8 = 0x03;
+const ATYP_IPV6: u8 = 0x04;
+
+/// Destination of the tunnel, either resolved by the client or left to the proxy.
+pub enum Destination<'a> {
+    Address(SocketAddr),
+    Domain(&'a str, u16),
+}
+
+/// Ask the SOCKS5 proxy at the other end of `stream` to connect to `destination`.
+pub async fn connect(
+    mut stream: TcpStream,
+    destination: Destination<'_>,
+    credentials: Option<(&str, &str)>,
+) -> Result<TcpStream, SilqError> {
+    let method = if credentials.is_some() {
+        USERNAME_PASSWORD
+    } else {
+        NO_AUTHENTICATION
+    };
+    write(&mut stream, &[VERSION, 1, method]).await?;
+
+    let mut reply = [0; 2];
+    read(&mut stream, &mut reply).await?;
+    match reply {
+        [VERSION, NO_ACCEPTABLE_METHOD] => Err(SilqError::new(
+            "SOCKS5 proxy rejected the authentication method".to_string(),
+        ))?,
+        [VERSION, selected] if selected == method => {}
+        _ => Err(SilqError::new("Invalid SOCKS5 proxy reply".to_string()))?,
+    }
+
+    if let Some((user, password)) = credentials {
+        let (user, password) = (user.as_bytes(), password.as_bytes());
+        if user.len() > 255 || password.len() > 255 {
+            Err(SilqError::new(
+                "SOCKS5 credentials can't exceed 255 bytes".to_string(),
+            ))?
+        }
+        let mut request = vec![USERNAME_PASSWORD_VERSION, user.len() as u8];
+        request.extend_from_slice(user);
+        request.push(password.len() as u8);
+        request.extend_from_slice(password);
+        write(&mut stream, &request).await?;
+
+        let mut reply = [0; 2];
+        read(&mut stream, &mut reply).await?;
+        if reply[1] != 0 {
+            Err(SilqError::new(
+                "SOCKS5 proxy authentication failed".to_string(),
+            ))?
+        }
+    }
+
+    let mut request = vec![VERSION, CONNECT, 0];
+    let port = match destination {
+        Destination::Address(address) => {
+            match address.ip() {
+                IpAddr::V4(ip) => {
+                    request.push(ATYP_IPV4);
+                    request.extend_from_slice(&ip.octets());
+                }
+                IpAddr::V6(ip) => {
+                    request.push(ATYP_IPV6);
+                    request.extend_from_slice(&ip.octets());
+                }
+            }
+            address.port()
+        }
+        Destination::Domain(domain, port) => {
+            if domain.len() > 255 {
+                Err(SilqError::new(
+                    "SOCKS5 domain name can't exceed 255 bytes".to_string(),
+                ))?
+            }
+            request.push(ATYP_DOMAIN);
+            request.push(domain.len() as u8);
+            request.extend_from_slice(domain.as_bytes());
+            port
+        }
+    };
+    request.extend_from_slice(&port.to_be_bytes());
+    write(&mut stream, &request).await?;
+
+    let mut reply = [0; 4];
+    read(&mut stream, &mut reply).await?;
+    if reply[0] != VERSION {
+        Err(SilqError::new("Invalid SOCKS5 proxy reply".to_string()))?
+    }
+    if reply[1] != 0 {
+        Err(SilqError::new(format!(
+            "SOCKS5 proxy refused to connect: {}",
+            describe_reply(reply[1])
+        )))?
+    }
+
+    // Skip the address bound by the proxy, useless for CONNECT
+    let length = match reply[3] {
+        ATYP_IPV4 => 4,
+        ATYP_IPV6 => 16,
+        ATYP_DOMAIN => {
+            let mut length = [0; 1];
+            read(&mut stream, &mut length).await?;
+            length[0] as usize
+        }
+        _ => Err(SilqError::new("Invalid SOCKS5 proxy reply".to_string()))?,
+    };
+    let mut bound = vec![0; length + 2];
+    read(&mut stream, &mut bound).await?;
+
+    Ok(stream)
+}
+
+fn describe_reply(reply: u8) -> &'static str {
+    match reply {
+        0x01 => "general failure",
+        0x02 => "connection not allowed by ruleset",
+        0x03 => "network unreachable",
+        0x04 => "host unreachable",
+        0x05 => "connection refused",
+        0x06 => "TTL expired",
+        0x07 => "command not supported",
+        0x08 => "address type not supported",
+        _ => "unknown error",
+    }
+}
+
+async fn write(stream: &mut TcpStream, bytes: &[u8]) -> Result<(), SilqError> {
+    stream
+        .write_all(bytes)
+        .await
+        .map_err(|err| SilqError::from("Unable to write to SOCKS5 proxy", &err))
+}
+
+async fn read(stream: &mut TcpStream, buffer: &mut [u8]) -> Result<(), SilqError> {
+    stream
+        .read_exact(buffer)
+        .await
+        .map(|_| ())
+        .map_err(|err| SilqError::from("Unable to read from SOCKS5 proxy", &err))
+}
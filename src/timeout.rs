@@ -0,0 +1,47 @@
+//! Bound each step of a request in time.
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use crate::error::{SilqError, Timeout};
+
+#[derive(Clone, Copy, Default)]
+pub struct Timeouts {
+    /// Time to establish the TCP connection.
+    pub connect: Option<Duration>,
+    /// Time to complete the TLS handshake.
+    pub tls_handshake: Option<Duration>,
+    /// Time between sending the request and receiving the response's head.
+    pub response: Option<Duration>,
+    /// Idle time allowed between two frames of the response's body.
+    pub read: Option<Duration>,
+    /// Time for the whole exchange, from sending the request to reading the end of the body.
+    pub total: Option<Duration>,
+}
+
+impl Timeouts {
+    pub fn deadline(&self) -> Option<Instant> {
+        self.total.map(|total| Instant::now() + total)
+    }
+}
+
+/// Run the given future within `duration`, without going past the `deadline`.
+pub async fn within<F: Future>(
+    timeout: Timeout,
+    duration: Option<Duration>,
+    deadline: Option<Instant>,
+    future: F,
+) -> Result<F::Output, SilqError> {
+    let limit = duration.map(|duration| (Instant::now() + duration, timeout));
+    let limit = match (limit, deadline) {
+        (Some((at, _)), Some(deadline)) if deadline < at => Some((deadline, Timeout::Deadline)),
+        (None, Some(deadline)) => Some((deadline, Timeout::Deadline)),
+        (limit, _) => limit,
+    };
+
+    match limit {
+        None => Ok(future.await),
+        Some((at, timeout)) => tokio::time::timeout_at(at.into(), future)
+            .await
+            .map_err(|_| SilqError::timeout(timeout)),
+    }
+}
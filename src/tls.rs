@@ -0,0 +1,61 @@
+//! TLS configuration shared by all the connections of a client.
+use std::sync::Arc;
+
+use tokio_rustls::rustls::{client::Resumption, ClientConfig, OwnedTrustAnchor, RootCertStore};
+use webpki_roots::TLS_SERVER_ROOTS;
+
+use crate::{connection::HttpVersionPolicy, error::SilqError, TransportSecurity};
+
+/// Number of TLS sessions remembered to resume handshakes with already visited servers.
+const SESSION_CACHE_SIZE: usize = 256;
+
+pub fn client_config(
+    transport_security: &TransportSecurity,
+    http_version_policy: HttpVersionPolicy,
+) -> Result<Arc<ClientConfig>, SilqError> {
+    let root_store = {
+        let mut root_store = RootCertStore::empty();
+        if let TransportSecurity::SecureOnly {
+            ca_cert: Some(ca_cert),
+            ..
+        } = transport_security
+        {
+            root_store
+                .add(&ca_cert.certificate)
+                .map_err(|err| SilqError::from("Unable to use specified CA Certificate", &err))?;
+        } else {
+            root_store.add_trust_anchors(TLS_SERVER_ROOTS.iter().map(|ta| {
+                OwnedTrustAnchor::from_subject_spki_name_constraints(
+                    ta.subject,
+                    ta.spki,
+                    ta.name_constraints,
+                )
+            }));
+        }
+        root_store
+    };
+
+    let mut tls_config = match transport_security {
+        TransportSecurity::SecureOnly {
+            client_identity: Some(identity),
+            ..
+        } => ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(root_store)
+            .with_client_auth_cert(
+                vec![identity.certificate.clone()],
+                identity.private_key.clone(),
+            )
+            .map_err(|err| SilqError::from("Unable to use client identity", &err))?,
+
+        _ => ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(root_store)
+            .with_no_client_auth(),
+    };
+
+    tls_config.alpn_protocols = http_version_policy.alpn_protocols();
+    tls_config.resumption = Resumption::in_memory_sessions(SESSION_CACHE_SIZE);
+
+    Ok(Arc::new(tls_config))
+}
@@ -0,0 +1,78 @@
+<?php
+use Silq\AwsSigner;
+use Silq\HttpClient;
+
+beforeEach(function () {
+    $this->signer = new AwsSigner('silq', 'silq-secret', 'us-east-1', 's3');
+    $this->client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withAwsSigner($this->signer)
+        ->build();
+    $this->bucket = 'http://localhost:9000/silq-' . bin2hex(random_bytes(4));
+    $this->client->put($this->bucket)->send();
+});
+
+test('sign requests', function () {
+    $upload = $this->client->put("$this->bucket/object.txt")->withBody('signed content')->send();
+    $download = $this->client->get("$this->bucket/object.txt")->send();
+
+    expect($upload->getStatusCode())->toBe(200);
+    expect($download->getStatusCode())->toBe(200);
+    expect($download->getText())->toBe('signed content');
+});
+
+test('sign encoded paths and queries', function () {
+    $this->client->put("$this->bucket/some%20dir/file%2Bname.txt")->withBody('content')->send();
+
+    $response = $this->client->get("$this->bucket?list-type=2&prefix=some%20dir%2F")->send();
+
+    expect($response->getStatusCode())->toBe(200);
+    expect($response->getText())->toContain('<Key>some dir/file+name.txt</Key>');
+});
+
+test('sign streamed bodies as unsigned payload', function () {
+    $stream = fopen('php://memory', 'r+');
+    fwrite($stream, 'streamed content');
+    rewind($stream);
+
+    $response = $this->client->put("$this->bucket/streamed.txt")
+        ->withBodyFromStream($stream, strlen('streamed content'))
+        ->send();
+    fclose($stream);
+
+    expect($response->getStatusCode())->toBe(200);
+    expect($this->client->get("$this->bucket/streamed.txt")->send()->getText())->toBe('streamed content');
+});
+
+test('reject wrong credentials', function () {
+    $response = $this->client->get("$this->bucket?list-type=2")
+        ->withAwsSigner(new AwsSigner('silq', 'wrong-secret', 'us-east-1', 's3'))
+        ->send();
+
+    expect($response->getStatusCode())->toBe(403);
+    expect($response->getText())->toContain('SignatureDoesNotMatch');
+});
+
+test('sign single requests', function () {
+    $client = HttpClient::builder()->allowUnsecureHttp(true)->build();
+
+    $response = $client->get("$this->bucket?list-type=2")->withAwsSigner($this->signer)->send();
+
+    expect($response->getStatusCode())->toBe(200);
+});
+
+test('presign URLs', function () {
+    $client = HttpClient::builder()->allowUnsecureHttp(true)->build();
+
+    $upload = $this->signer->presign('PUT', "$this->bucket/presigned.txt", 60);
+    expect($client->put($upload)->withBody('presigned content')->send()->getStatusCode())->toBe(200);
+
+    $download = $this->signer->presign('GET', "$this->bucket/presigned.txt");
+    expect($download)->toContain('X-Amz-Expires=3600');
+    expect($client->get($download)->send()->getText())->toBe('presigned content');
+});
+
+test('reject invalid presigned URL expiration', function () {
+    expect(fn () => $this->signer->presign('GET', "$this->bucket/object.txt", 8 * 24 * 3600))
+        ->toThrow(Exception::class, 'Silq Exception: Presigned URL expiration must be between 1 second and 7 days');
+});
@@ -0,0 +1,87 @@
+<?php
+use Silq\HttpClient;
+
+test('compress body', function (string $codec, ?int $level, callable $decode) {
+    $content = str_repeat("Some content\non multiple lines\n", 100);
+    $json = sendToUnixServer(fn ($request) => $request
+        ->withBody($content)
+        ->withCompression($codec, $level));
+
+    $body = base64_decode($json['body']);
+    expect($json['headers']['content-encoding'])->toBe($codec);
+    expect($json['headers']['content-length'])->toBe((string) strlen($body));
+    expect(strlen($body))->toBeLessThan(strlen($content));
+    expect($decode($body))->toBe($content);
+})->with([
+    'gzip' => ['gzip', null, fn ($body) => gzdecode($body)],
+    'gzip level 9' => ['gzip', 9, fn ($body) => gzdecode($body)],
+    'deflate' => ['deflate', 1, fn ($body) => gzuncompress($body)],
+]);
+
+test('compress body with brotli', function () {
+    $content = str_repeat('{"key":"value"}', 100);
+    $json = sendToUnixServer(fn ($request) => $request
+        ->withBody($content)
+        ->withCompression('br', 11));
+
+    expect($json['headers']['content-encoding'])->toBe('br');
+    expect(brotli_uncompress(base64_decode($json['body'])))->toBe($content);
+})->skip(!function_exists('brotli_uncompress'), 'brotli extension is not installed');
+
+test('compress body with zstd', function () {
+    $content = str_repeat('{"key":"value"}', 100);
+    $json = sendToUnixServer(fn ($request) => $request
+        ->withBody($content)
+        ->withCompression('zstd'));
+
+    expect($json['headers']['content-encoding'])->toBe('zstd');
+    expect(zstd_uncompress(base64_decode($json['body'])))->toBe($content);
+})->skip(!function_exists('zstd_uncompress'), 'zstd extension is not installed');
+
+test('compress JSON body', function () {
+    $content = ['key' => str_repeat('value', 100)];
+    $json = sendToUnixServer(fn ($request) => $request
+        ->withJson($content)
+        ->withCompression('gzip'));
+
+    expect($json['headers']['content-type'])->toBe('application/json');
+    expect(json_decode(gzdecode(base64_decode($json['body'])), true))->toBe($content);
+});
+
+test('replace explicit content length', function () {
+    $content = str_repeat('a', 1000);
+    $json = sendToUnixServer(fn ($request) => $request
+        ->withHeaders(['content-length' => '1000'])
+        ->withBody($content)
+        ->withCompression('gzip'));
+
+    expect($json['headers']['content-length'])->toBe((string) strlen(base64_decode($json['body'])));
+    expect(gzdecode(base64_decode($json['body'])))->toBe($content);
+});
+
+test('leave empty body uncompressed', function () {
+    $json = sendToUnixServer(fn ($request) => $request->withCompression('gzip'));
+
+    expect($json['headers'])->not->toHaveKey('content-encoding');
+    expect($json['body'])->toBe('');
+});
+
+test('reject unknown codec', function () {
+    $client = HttpClient::builder()->build();
+    $request = $client->post('https://localhost:8443/');
+
+    expect(fn () => $request->withCompression('lzma'))
+        ->toThrow(Exception::class, 'Silq Exception: Unsupported compression codec: lzma, expected gzip, deflate, br or zstd');
+});
+
+test('reject invalid level', function (string $codec, int $level, string $message) {
+    $client = HttpClient::builder()->build();
+    $request = $client->post('https://localhost:8443/');
+
+    expect(fn () => $request->withCompression($codec, $level))
+        ->toThrow(Exception::class, "Silq Exception: $message");
+})->with([
+    'gzip' => ['gzip', 10, 'Invalid gzip compression level: 10, expected 0 to 9'],
+    'br' => ['br', -1, 'Invalid br compression level: -1, expected 0 to 11'],
+    'zstd' => ['zstd', 0, 'Invalid zstd compression level: 0, expected 1 to 22'],
+]);
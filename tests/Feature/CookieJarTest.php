@@ -0,0 +1,152 @@
+<?php
+use Silq\CookieJar;
+use Silq\HttpClient;
+
+beforeEach(function () {
+    $this->jar = new CookieJar();
+    $this->client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withRedirectPolicy(5)
+        ->withCookieJar($this->jar)
+        ->build();
+});
+
+test('store cookies set by responses', function () {
+    $this->client->get('http://localhost:8082/cookies/set')->send();
+
+    // The secure cookie is ignored, set over unsecure HTTP
+    expect($this->jar->count())->toBe(3);
+    expect($this->jar->getCookieHeader('http://localhost:8082/cookies/echo'))->toBe('theme=dark; session=abc123');
+});
+
+test('send stored cookies with later requests', function () {
+    $this->client->get('http://localhost:8082/cookies/set')->send();
+
+    $cookies = $this->client->get('http://localhost:8082/cookies/echo')->send()->getText();
+
+    expect($cookies)->toBe('theme=dark; session=abc123');
+});
+
+test('scope cookies to their path', function () {
+    $this->client->get('http://localhost:8082/cookies/set')->send();
+
+    $cookies = $this->client->get('http://localhost:8082/cookies/private/echo')->send()->getText();
+
+    expect($cookies)->toBe('scoped=1; theme=dark; session=abc123');
+});
+
+test('append stored cookies to the request ones', function () {
+    $this->client->get('http://localhost:8082/cookies/set')->send();
+
+    $cookies = $this->client->get('http://localhost:8082/cookies/echo')
+        ->withRawCookies(['own' => 'value'])
+        ->send()
+        ->getText();
+
+    expect($cookies)->toBe('own=value; theme=dark; session=abc123');
+});
+
+test('remove expired cookies', function () {
+    $this->client->get('http://localhost:8082/cookies/set')->send();
+    $this->client->get('http://localhost:8082/cookies/expire')->send();
+
+    expect($this->jar->getCookieHeader('http://localhost:8082/cookies/echo'))->toBe('theme=dark');
+});
+
+test('store and send cookies across redirects', function () {
+    $response = $this->client->get('http://localhost:8082/cookies/redirect')->send();
+
+    expect($response->getText())->toBe('redirected=yes');
+});
+
+test('only send cookies to their host', function () {
+    $this->client->get('http://localhost:8082/cookies/set')->send();
+
+    expect($this->jar->getCookieHeader('http://127.0.0.1:8082/cookies/echo'))->toBeNull();
+});
+
+test('ignore cookies set for public suffixes', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withDnsOverride('evil.co.uk', ['127.0.0.1'])
+        ->withCookieJar($this->jar)
+        ->build();
+    $client->get('http://evil.co.uk:8082/cookies/public-suffix')->send();
+
+    expect($this->jar->count())->toBe(1);
+    expect($this->jar->getCookieHeader('http://www.evil.co.uk/'))->toBe('site=1');
+    expect($this->jar->getCookieHeader('http://other.co.uk/'))->toBeNull();
+});
+
+test('share the jar between clients', function () {
+    $this->client->get('http://localhost:8082/cookies/set')->send();
+    $other = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withCookieJar($this->jar)
+        ->build();
+
+    expect($other->get('http://localhost:8082/cookies/echo')->send()->getText())->toBe('theme=dark; session=abc123');
+});
+
+test('export and import cookies as JSON', function () {
+    $this->client->get('http://localhost:8082/cookies/set')->send();
+
+    $json = $this->jar->toJson();
+    $cookies = json_decode($json, true);
+    expect(array_column($cookies, 'name'))->toBe(['session', 'theme', 'scoped']);
+    expect($cookies[0])->toMatchArray([
+        'value' => 'abc123',
+        'domain' => 'localhost',
+        'hostOnly' => true,
+        'path' => '/',
+        'expires' => null,
+        'httpOnly' => true,
+        'secure' => false,
+    ]);
+    expect($cookies[1]['expires'])->toBeGreaterThan(time() + 3500);
+
+    $imported = CookieJar::fromJson($json);
+    expect($imported->getCookieHeader('http://localhost:8082/cookies/private/echo'))
+        ->toBe('scoped=1; theme=dark; session=abc123');
+});
+
+test('export and import cookies in the Netscape format', function () {
+    $this->client->get('http://localhost:8082/cookies/set')->send();
+
+    $contents = $this->jar->toNetscape();
+    expect($contents)->toStartWith("# Netscape HTTP Cookie File\n");
+    expect($contents)->toContain("#HttpOnly_localhost\tFALSE\t/\tFALSE\t0\tsession\tabc123\n");
+
+    $imported = CookieJar::fromNetscape($contents);
+    expect($imported->getCookieHeader('http://localhost:8082/'))->toBe('session=abc123');
+    expect($imported->getCookieHeader('http://localhost:8082/cookies/echo'))->toBe('theme=dark; session=abc123');
+});
+
+test('import cookies for a domain and its subdomains', function () {
+    $jar = CookieJar::fromNetscape(implode("\n", [
+        '# Netscape HTTP Cookie File',
+        ".example.com\tTRUE\t/\tFALSE\t0\tdomain\t1",
+        "example.com\tFALSE\t/\tTRUE\t0\thost\t2",
+        "example.com\tFALSE\t/\tFALSE\t1\texpired\t3",
+    ]));
+
+    expect($jar->count())->toBe(2);
+    expect($jar->getCookieHeader('http://www.example.com/'))->toBe('domain=1');
+    expect($jar->getCookieHeader('https://example.com/'))->toBe('domain=1; host=2');
+});
+
+test('reject invalid exports', function () {
+    expect(fn () => CookieJar::fromJson('{'))
+        ->toThrow(Exception::class, 'Silq Exception: Invalid cookie jar JSON');
+    expect(fn () => CookieJar::fromJson('[{"name": "missing value"}]'))
+        ->toThrow(Exception::class, 'Silq Exception: Invalid cookie: missing value');
+    expect(fn () => CookieJar::fromNetscape("not\ta cookie"))
+        ->toThrow(Exception::class, 'Silq Exception: Invalid Netscape cookie on line 1');
+});
+
+test('clear cookies', function () {
+    $this->client->get('http://localhost:8082/cookies/set')->send();
+    $this->jar->clear();
+
+    expect($this->jar->count())->toBe(0);
+});
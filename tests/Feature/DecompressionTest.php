@@ -0,0 +1,95 @@
+<?php
+use Silq\HttpClient;
+
+beforeAll(function () {
+    global $decompressedBody;
+    $decompressedBody = file_get_contents('tests/data/encoded/body.json');
+});
+
+test('advertise supported encodings', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->build();
+    $json = $client->get('http://localhost:8080/')->send()->getJson();
+
+    expect($json['headers']['accept-encoding'])->toBe('gzip, deflate, br, zstd');
+});
+
+test('keep explicit accept encoding header', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->build();
+    $json = $client->get('http://localhost:8080/')
+        ->withHeaders(['accept-encoding' => 'gzip'])
+        ->send()
+        ->getJson();
+
+    expect($json['headers']['accept-encoding'])->toBe('gzip');
+});
+
+test('decode compressed body', function (string $path, string $encoding) {
+    global $decompressedBody;
+
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->build();
+    $response = $client->get("http://localhost:8083/$path")->send();
+
+    expect($response->isDecompressed())->toBeTrue();
+    expect($response->getHeaderFirstValue('content-encoding'))->toBe($encoding);
+    expect($response->getText())->toBe($decompressedBody);
+})->with([
+    ['gzip', 'gzip'],
+    ['deflate', 'deflate'],
+    ['raw-deflate', 'deflate'],
+    ['br', 'br'],
+    ['zstd', 'zstd'],
+    ['zstd-gzip', 'zstd, gzip'],
+]);
+
+test('decode compressed body frame by frame', function () {
+    global $decompressedBody;
+
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->build();
+    $response = $client->get('http://localhost:8083/gzip')->send();
+
+    $body = '';
+    foreach ($response->iterFrames() as $frame) {
+        $body .= $frame;
+    }
+    expect($body)->toBe($decompressedBody);
+});
+
+test('leave body encoded when decompression is disabled', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withDecompression(false)
+        ->build();
+
+    $json = $client->get('http://localhost:8080/')->send()->getJson();
+    expect($json['headers'])->not->toHaveKey('accept-encoding');
+
+    $response = $client->get('http://localhost:8083/gzip')->send();
+    expect($response->isDecompressed())->toBeFalse();
+    expect($response->getBytes())->toBe(file_get_contents('tests/data/encoded/body.json.gz'));
+
+    $response = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->build()
+        ->get('http://localhost:8083/gzip')
+        ->withDecompression(false)
+        ->send();
+    expect($response->isDecompressed())->toBeFalse();
+});
+
+test('leave body with unknown encoding as-is', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->build();
+    $response = $client->get('http://localhost:8083/unknown')->send();
+
+    expect($response->isDecompressed())->toBeFalse();
+    expect($response->getBytes())->toBe(file_get_contents('tests/data/encoded/body.json.gz'));
+});
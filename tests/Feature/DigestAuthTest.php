@@ -0,0 +1,112 @@
+<?php
+use Silq\HttpClient;
+
+beforeEach(function () {
+    $this->path = sys_get_temp_dir() . '/silq-test-digest-' . getmypid() . '.sock';
+    $this->client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->build();
+});
+
+afterEach(function () {
+    proc_terminate($this->server);
+    proc_close($this->server);
+    @unlink($this->path);
+});
+
+function startDigestServer(string $path, string $algorithms, string $qop = 'auth', bool $stale = false)
+{
+    $args = [$algorithms, $qop];
+    if ($stale) {
+        $args[] = 'stale';
+    }
+    return startUnixServer($path, 'tests/data/digest-server.php', $args);
+}
+
+test('answer digest challenges', function (string $algorithm) {
+    $this->server = startDigestServer($this->path, $algorithm);
+
+    $response = $this->client->get('http://localhost/protected?page=1')
+        ->withUnixSocket($this->path)
+        ->withDigestAuth('alice', 'secret')
+        ->send();
+
+    expect($response->getStatusCode())->toBe(200);
+    expect($response->getJson())->toBe(['user' => 'alice', 'nc' => '00000001', 'requests' => 2]);
+})->with(['MD5', 'SHA-256', 'SHA-512-256', 'MD5-sess', 'SHA-256-sess']);
+
+test('choose the strongest algorithm offered', function () {
+    $this->server = startDigestServer($this->path, 'MD5,SHA-256,UNKNOWN');
+
+    $response = $this->client->get('http://localhost/protected')
+        ->withUnixSocket($this->path)
+        ->withDigestAuth('alice', 'secret')
+        ->send();
+
+    expect($response->getStatusCode())->toBe(200);
+});
+
+test('protect the integrity of the body', function () {
+    $this->server = startDigestServer($this->path, 'SHA-256', 'auth-int');
+
+    $response = $this->client->post('http://localhost/protected')
+        ->withUnixSocket($this->path)
+        ->withDigestAuth('alice', 'secret')
+        ->withBody('{"integrity": "protected"}')
+        ->send();
+
+    expect($response->getStatusCode())->toBe(200);
+});
+
+test('return the challenge for wrong credentials', function () {
+    $this->server = startDigestServer($this->path, 'SHA-256');
+
+    $response = $this->client->get('http://localhost/protected')
+        ->withUnixSocket($this->path)
+        ->withDigestAuth('alice', 'wrong')
+        ->send();
+
+    expect($response->getStatusCode())->toBe(401);
+    expect($response->getJson()['requests'])->toBe(2);
+    expect($response->getHeaderFirstValue('www-authenticate'))->toContain('Digest realm="silq@localhost"');
+});
+
+test('reuse the nonce for later requests', function () {
+    $this->server = startDigestServer($this->path, 'SHA-256');
+    $send = fn () => $this->client->get('http://localhost/protected')
+        ->withUnixSocket($this->path)
+        ->withDigestAuth('alice', 'secret')
+        ->send()
+        ->getJson();
+
+    expect($send())->toBe(['user' => 'alice', 'nc' => '00000001', 'requests' => 2]);
+    expect($send())->toBe(['user' => 'alice', 'nc' => '00000002', 'requests' => 3]);
+});
+
+test('renew stale nonces', function () {
+    $this->server = startDigestServer($this->path, 'SHA-256', 'auth', true);
+    $send = fn () => $this->client->get('http://localhost/protected')
+        ->withUnixSocket($this->path)
+        ->withDigestAuth('alice', 'secret')
+        ->send()
+        ->getJson();
+
+    expect($send())->toBe(['user' => 'alice', 'nc' => '00000001', 'requests' => 2]);
+    expect($send())->toBe(['user' => 'alice', 'nc' => '00000001', 'requests' => 4]);
+});
+
+test('return the challenge for streamed bodies', function () {
+    $this->server = startDigestServer($this->path, 'SHA-256');
+    $stream = fopen('php://memory', 'r+');
+    fwrite($stream, 'streamed content');
+    rewind($stream);
+
+    $response = $this->client->post('http://localhost/protected')
+        ->withUnixSocket($this->path)
+        ->withDigestAuth('alice', 'secret')
+        ->withBodyFromStream($stream)
+        ->send();
+    fclose($stream);
+
+    expect($response->getStatusCode())->toBe(401);
+});
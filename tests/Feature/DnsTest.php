@@ -0,0 +1,114 @@
+<?php
+use Silq\HttpClient;
+use Silq\CertificateAuthority;
+use Silq\ClientIdentity;
+
+test('resolve host name to overridden address', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withDnsOverride('silq.test', ['127.0.0.1'])
+        ->build();
+    $response = $client->get('http://silq.test:8080/')->send();
+
+    expect($response->getStatusCode())->toBe(200);
+    expect($response->getJson()['headers']['host'])->toBe('silq.test:8080');
+});
+
+test('try overridden addresses in order', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withDnsOverride('silq.test', ['::1', '127.0.0.1'])
+        ->build();
+    $response = $client->get('http://silq.test:8080/')->send();
+
+    expect($response->getStatusCode())->toBe(200);
+});
+
+test('reject invalid overridden address', function () {
+    expect(fn() => HttpClient::builder()->withDnsOverride('silq.test', ['not-an-ip']))
+        ->toThrow(Exception::class, 'Silq Exception: Invalid IP address');
+});
+
+test('verify certificate against original host name', function () {
+    $ca = CertificateAuthority::fromPem(file_get_contents('tests/data/ca-crt.pem'));
+    $identity = ClientIdentity::fromPem(
+        file_get_contents('tests/data/client1-crt.pem'),
+        file_get_contents('tests/data/client1-key.pem'),
+    );
+
+    $client = HttpClient::builder()
+        ->withServerAuthentication($ca)
+        ->withClientAuthentication($identity)
+        ->withDnsOverride('localhost', ['127.0.0.1'])
+        ->withDnsOverride('silq.test', ['127.0.0.1'])
+        ->build();
+
+    expect($client->get('https://localhost:8443/')->send()->getStatusCode())->toBe(200);
+    expect(fn() => $client->get('https://silq.test:8443/')->send())
+        ->toThrow(Exception::class, 'Silq Exception: Connection error');
+});
+
+test('resolve host names with callable', function () {
+    $resolved = [];
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withDnsResolver(function (string $host) use (&$resolved) {
+            $resolved[] = $host;
+            return $host === 'silq.test' ? '127.0.0.1' : null;
+        })
+        ->withPoolMaxIdlePerHost(0)
+        ->build();
+
+    expect($client->get('http://silq.test:8080/')->send()->getStatusCode())->toBe(200);
+    expect($client->get('http://localhost:8080/')->send()->getStatusCode())->toBe(200);
+    expect($resolved)->toBe(['silq.test', 'localhost']);
+});
+
+test('reject invalid resolver result', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withDnsResolver(fn(string $host) => 42)
+        ->build();
+
+    expect(fn() => $client->get('http://silq.test:8080/')->send())
+        ->toThrow(Exception::class, 'Silq Exception: DNS resolver must return a string, an array of strings or null');
+});
+
+test('cache resolved addresses', function () {
+    $calls = 0;
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withDnsResolver(function (string $host) use (&$calls) {
+            $calls++;
+            return ['127.0.0.1'];
+        })
+        ->withDnsCacheTtl(60.0)
+        ->withPoolMaxIdlePerHost(0)
+        ->build();
+
+    $client->get('http://silq.test:8080/')->send();
+    $client->get('http://silq.test:8080/')->send();
+
+    expect($calls)->toBe(1);
+});
+
+test('race addresses when the first one does not answer', function () {
+    // 192.0.2.0/24 is reserved for documentation, connections to it never complete
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withDnsOverride('silq.test', ['192.0.2.1', '127.0.0.1'])
+        ->withConnectAttemptDelay(0.05)
+        ->withConnectTimeout(5.0)
+        ->build();
+
+    $start = microtime(true);
+    $response = $client->get('http://silq.test:8080/')->send();
+
+    expect($response->getStatusCode())->toBe(200);
+    expect(microtime(true) - $start)->toBeLessThan(1.0);
+});
+
+test('reject negative connect attempt delay', function () {
+    expect(fn() => HttpClient::builder()->withConnectAttemptDelay(-1.0))
+        ->toThrow(Exception::class, 'Silq Exception: Invalid duration');
+});
@@ -0,0 +1,110 @@
+<?php
+use Silq\EventLoop;
+use Silq\HttpClient;
+
+beforeEach(function () {
+    $this->client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->build();
+});
+
+afterEach(function () {
+    EventLoop::setSuspender(null);
+});
+
+/**
+ * Minimal event loop, resuming the Fibers suspended by Silq when their descriptor is readable.
+ */
+function runFibers(array $fibers): array
+{
+    $waiting = [];
+    EventLoop::setSuspender(function (int $fd) use (&$waiting) {
+        $waiting[] = [$fd, Fiber::getCurrent()];
+        Fiber::suspend();
+    });
+
+    foreach ($fibers as $fiber) {
+        $fiber->start();
+    }
+    while ($waiting) {
+        $streams = array_map(fn ($waiter) => fopen("php://fd/{$waiter[0]}", 'r'), $waiting);
+        $read = $streams;
+        $write = $except = null;
+        stream_select($read, $write, $except, 5);
+        array_walk($streams, 'fclose');
+
+        [$resumed, $waiting] = [$waiting, []];
+        foreach ($resumed as [, $fiber]) {
+            $fiber->resume();
+        }
+    }
+
+    return array_map(fn ($fiber) => $fiber->getReturn(), $fibers);
+}
+
+test('suspend fibers instead of blocking', function () {
+    $send = fn (string $path) => new Fiber(fn () => $this->client->get("http://localhost:8080$path")
+        ->withHeaders(['x-set-response-delay-ms' => '300'])
+        ->send()
+        ->getJson()['path']);
+
+    $start = microtime(true);
+    $paths = runFibers([$send('/first'), $send('/second'), $send('/third')]);
+
+    expect($paths)->toBe(['/first', '/second', '/third']);
+    expect(microtime(true) - $start)->toBeLessThan(0.8);
+});
+
+test('suspend fibers reading frames', function () {
+    $read = fn (string $path) => new Fiber(function () use ($path) {
+        $response = $this->client->get("http://localhost:8080$path")->send();
+        $body = '';
+        foreach ($response->iterFrames() as $frame) {
+            $body .= $frame;
+        }
+        return json_decode($body, true)['path'];
+    });
+
+    expect(runFibers([$read('/first'), $read('/second')]))->toBe(['/first', '/second']);
+});
+
+test('suspend fibers waiting for futures', function () {
+    $future = $this->client->get('http://localhost:8080/async')
+        ->withHeaders(['x-set-response-delay-ms' => '200'])
+        ->sendAsync();
+
+    $paths = runFibers([new Fiber(fn () => $future->wait()->getJson()['path'])]);
+
+    expect($paths)->toBe(['/async']);
+});
+
+test('call the suspender with a readable descriptor', function () {
+    $calls = 0;
+    EventLoop::setSuspender(function (int $fd) use (&$calls) {
+        $calls++;
+        $stream = fopen("php://fd/$fd", 'r');
+        $read = [$stream];
+        $write = $except = null;
+        stream_select($read, $write, $except, 5);
+        fclose($stream);
+    });
+
+    $response = $this->client->get('http://localhost:8080')
+        ->withHeaders(['x-set-response-delay-ms' => '100'])
+        ->send();
+
+    expect($response->getStatusCode())->toBe(200);
+    expect($calls)->toBeGreaterThan(0);
+});
+
+test('block again without suspender', function () {
+    EventLoop::setSuspender(fn () => throw new LogicException('Not expected'));
+    EventLoop::setSuspender(null);
+
+    expect($this->client->get('http://localhost:8080')->send()->getStatusCode())->toBe(200);
+});
+
+test('reject invalid suspender', function () {
+    expect(fn () => EventLoop::setSuspender('not a callable'))
+        ->toThrow(Exception::class, 'Silq Exception: Event loop suspender must be callable');
+});
@@ -0,0 +1,148 @@
+<?php
+use Silq\Future;
+use Silq\HttpClient;
+use Silq\Response;
+
+beforeEach(function () {
+    $this->client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->build();
+});
+
+function delayed(HttpClient $client, int $ms, string $path = '/'): Future
+{
+    return $client->get("http://localhost:8080$path")
+        ->withHeaders(['x-set-response-delay-ms' => (string) $ms])
+        ->sendAsync();
+}
+
+test('send request in the background', function () {
+    $future = $this->client->get('http://localhost:8080/async')->sendAsync();
+
+    expect($future)->toBeInstanceOf(Future::class);
+    $response = $future->wait();
+    expect($response)->toBeInstanceOf(Response::class);
+    expect($response->getJson()['path'])->toBe('/async');
+    expect($future->isReady())->toBeTrue();
+});
+
+test('carry on requests while waiting for others', function () {
+    $start = microtime(true);
+    $first = delayed($this->client, 300, '/first');
+    $second = delayed($this->client, 300, '/second');
+    $response = $this->client->get('http://localhost:8080/sync')
+        ->withHeaders(['x-set-response-delay-ms' => '300'])
+        ->send();
+
+    expect($response->getJson()['path'])->toBe('/sync');
+    expect($first->wait()->getJson()['path'])->toBe('/first');
+    expect($second->wait()->getJson()['path'])->toBe('/second');
+    expect(microtime(true) - $start)->toBeLessThan(0.8);
+});
+
+test('report readiness without waiting', function () {
+    $future = delayed($this->client, 300);
+
+    expect($future->isReady())->toBeFalse();
+    usleep(500000);
+    expect($future->isReady())->toBeTrue();
+    expect($future->wait()->getStatusCode())->toBe(200);
+});
+
+test('throw failures when waiting', function () {
+    $future = $this->client->get('http://localhost:1')->sendAsync();
+
+    expect(fn () => $future->wait())->toThrow(Exception::class);
+});
+
+test('cancel a request', function () {
+    $future = delayed($this->client, 1000);
+
+    expect($future->cancel())->toBeTrue();
+    expect($future->isReady())->toBeTrue();
+    expect(fn () => $future->wait())
+        ->toThrow(Exception::class, 'Silq Exception: Request cancelled');
+});
+
+test('do not cancel a completed request', function () {
+    $future = delayed($this->client, 0);
+    $future->wait();
+
+    expect($future->cancel())->toBeFalse();
+});
+
+test('take the response only once', function () {
+    $future = delayed($this->client, 0);
+    $future->wait();
+
+    expect(fn () => $future->wait())
+        ->toThrow(Exception::class, 'Silq Exception: Response already taken from this future');
+});
+
+test('wait for all futures', function () {
+    $start = microtime(true);
+    $responses = Future::all([
+        'first' => delayed($this->client, 300, '/first'),
+        3 => delayed($this->client, 200, '/second'),
+    ]);
+
+    expect(microtime(true) - $start)->toBeLessThan(0.6);
+    expect(array_keys($responses))->toBe(['first', 3]);
+    expect($responses['first']->getJson()['path'])->toBe('/first');
+    expect($responses[3]->getJson()['path'])->toBe('/second');
+});
+
+test('throw the first failure of all futures', function () {
+    $futures = [
+        delayed($this->client, 1000),
+        $this->client->get('http://localhost:1')->sendAsync(),
+    ];
+
+    $start = microtime(true);
+    expect(fn () => Future::all($futures))->toThrow(Exception::class);
+    expect(microtime(true) - $start)->toBeLessThan(0.8);
+});
+
+test('wait for any successful future', function () {
+    $response = Future::any([
+        $this->client->get('http://localhost:1')->sendAsync(),
+        delayed($this->client, 500, '/slow'),
+        delayed($this->client, 100, '/fast'),
+    ]);
+
+    expect($response->getJson()['path'])->toBe('/fast');
+});
+
+test('throw when no future succeeds', function () {
+    $futures = [
+        $this->client->get('http://localhost:1')->sendAsync(),
+        $this->client->get('http://localhost:2')->sendAsync(),
+    ];
+
+    expect(fn () => Future::any($futures))->toThrow(Exception::class);
+});
+
+test('race futures', function () {
+    $response = Future::race([
+        delayed($this->client, 500, '/slow'),
+        delayed($this->client, 100, '/fast'),
+    ]);
+
+    expect($response->getJson()['path'])->toBe('/fast');
+});
+
+test('throw the failure winning the race', function () {
+    $futures = [
+        delayed($this->client, 1000),
+        $this->client->get('http://localhost:1')->sendAsync(),
+    ];
+
+    expect(fn () => Future::race($futures))->toThrow(Exception::class);
+});
+
+test('reject invalid futures', function () {
+    expect(fn () => Future::all(['not a future']))
+        ->toThrow(Exception::class, 'Silq Exception: Futures must be Future objects');
+    expect(fn () => Future::race([]))
+        ->toThrow(Exception::class, 'Silq Exception: No future to wait for');
+});
@@ -1,5 +1,7 @@
 <?php
+use Silq\CertificateAuthority;
 use Silq\HttpClient;
+use Silq\TimeoutException;
 
 function parseSafeCookies(string $rawCookies): array {
     $pairs = array_map(fn($cookie) => explode('=', $cookie), explode('; ', $rawCookies));
@@ -333,3 +335,182 @@ test('get HTTPS', function () {
 
     expect($response->getStatusCode())->toBe(200);
 });
+
+test('reuse pooled connections across requests', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withPoolIdleTimeout(5.0)
+        ->withPoolMaxIdlePerHost(2)
+        ->build();
+
+    foreach (range(0, 3) as $i) {
+        $response = $client->get("http://localhost:8080/pooled/$i")->send();
+        expect($response->getStatusCode())->toBe(200);
+        expect($response->getJson()['path'])->toBe("/pooled/$i");
+    }
+
+    // nginx numbers its connections
+    $connections = array_map(
+        fn () => $client->get('http://localhost:8082/connection')->send()->getText(),
+        range(0, 2),
+    );
+    expect(array_unique($connections))->toHaveCount(1);
+});
+
+test('open new connections without pooling', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withPoolMaxIdlePerHost(0)
+        ->build();
+
+    $connections = array_map(
+        fn () => $client->get('http://localhost:8082/connection')->send()->getText(),
+        range(0, 2),
+    );
+    expect(array_unique($connections))->toHaveCount(3);
+});
+
+test('send again requests over pooled connections closed by the server', function () {
+    $path = sys_get_temp_dir() . '/silq-test-stale-' . getmypid() . '.sock';
+    $server = startUnixServer($path, 'tests/data/stale-server.php');
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withUnixSocket($path)
+        ->build();
+
+    expect($client->get('http://localhost/')->send()->getText())->toBe('1');
+    // The server closes the pooled connection as the request arrives
+    $response = $client->get('http://localhost/')->send();
+
+    expect($response->getText())->toBe('2');
+    expect($response->getAttempts())->toBe(1);
+
+    proc_close($server);
+});
+
+test('unconsumed response body does not break following requests', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->build();
+
+    $first = $client->get('http://localhost:8080/first')->send();
+    expect($first->getStatusCode())->toBe(200);
+    unset($first);
+
+    $second = $client->get('http://localhost:8080/second')->send();
+    expect($second->getJson()['path'])->toBe('/second');
+});
+
+test('expose negotiated HTTP version', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->build();
+    $response = $client->get('http://localhost:8080')->send();
+    expect($response->getHttpVersion())->toBe('HTTP/1.1');
+});
+
+test('negotiate HTTP/2 through ALPN', function () {
+    $client = HttpClient::builder()
+        ->withServerAuthentication(CertificateAuthority::fromPem(file_get_contents('tests/data/ca-crt.pem')))
+        ->withHttpVersionPolicy('http2')
+        ->build();
+    $response = $client->get('https://localhost:8445/')->send();
+    expect($response->getHttpVersion())->toBe('HTTP/2.0');
+    expect($response->getText())->toBe('HTTP/2.0');
+
+    $response = $client->get('https://localhost:8445/')->send();
+    expect($response->getStatusCode())->toBe(200);
+});
+
+test('reject unknown HTTP version policy', function () {
+    expect(fn() => HttpClient::builder()->withHttpVersionPolicy('http3'))
+        ->toThrow(new Exception("Silq Exception: Unknown HTTP version policy 'http3', expected one of: http1, http2-preferred, http2"));
+});
+
+test('reject HTTP/2 only policy for unsecure HTTP', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withHttpVersionPolicy('http2')
+        ->build();
+    expect(fn() => $client->get('http://localhost:8080'))
+        ->toThrow(new Exception("Silq Exception: HTTP/2 can't be negotiated over unsecure HTTP"));
+});
+
+test('speak cleartext HTTP/2 with prior knowledge', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withHttpVersionPolicy('http2')
+        ->withHttp2PriorKnowledge(true)
+        ->build();
+    $response = $client->get('http://localhost:8081/')->send();
+
+    expect($response->getHttpVersion())->toBe('HTTP/2.0');
+    expect($response->getText())->toBe('HTTP/2.0');
+});
+
+test('reject HTTP/2 prior knowledge without unsecure HTTP', function () {
+    expect(fn() => HttpClient::builder()->withHttp2PriorKnowledge(true)->build())
+        ->toThrow(new Exception('Silq Exception: HTTP/2 prior knowledge requires unsecure HTTP to be allowed'));
+});
+
+test('raise timeout exception when connection takes too long', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withConnectTimeout(0.1)
+        ->build();
+
+    try {
+        $client->get('http://10.255.255.1/')->send();
+        $this->fail('Expected a timeout');
+    } catch (TimeoutException $e) {
+        expect($e->getCode())->toBe(TimeoutException::CONNECT);
+        expect($e->getMessage())->toBe('Silq Exception: Connection timed out');
+    }
+});
+
+test('raise timeout exception when response takes too long', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withResponseTimeout(5.0)
+        ->build();
+    $request = $client->get('http://localhost:8080')
+        ->withHeaders(['x-set-response-delay-ms' => '500'])
+        ->withResponseTimeout(0.1);
+
+    try {
+        $request->send();
+        $this->fail('Expected a timeout');
+    } catch (TimeoutException $e) {
+        expect($e->getCode())->toBe(TimeoutException::RESPONSE);
+    }
+});
+
+test('raise timeout exception when deadline is exceeded', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withTimeout(0.1)
+        ->build();
+    $request = $client->get('http://localhost:8080')
+        ->withHeaders(['x-set-response-delay-ms' => '500']);
+
+    try {
+        $request->send();
+        $this->fail('Expected a timeout');
+    } catch (TimeoutException $e) {
+        expect($e->getCode())->toBe(TimeoutException::DEADLINE);
+    }
+});
+
+test('complete requests within timeouts', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withConnectTimeout(1.0)
+        ->withResponseTimeout(1.0)
+        ->withReadTimeout(1.0)
+        ->withTimeout(2.0)
+        ->build();
+    $response = $client->get('http://localhost:8080')->send();
+
+    expect($response->getStatusCode())->toBe(200);
+    expect($response->getJson()['path'])->toBe('/');
+});
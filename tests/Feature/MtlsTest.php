@@ -74,4 +74,28 @@ test('raise exception on unknown issuer', function () {
 
     expect(fn() => $client->get('https://google.ch')->send())
         ->toThrow(new Exception('Silq Exception: Connection error: invalid peer certificate: UnknownIssuer'));
-});
\ No newline at end of file
+});
+
+test('raise exception on invalid CA certificate when building the client', function () {
+    $builder = HttpClient::builder()
+        ->withServerAuthentication(CertificateAuthority::fromBytes('not a certificate'));
+
+    expect(fn() => $builder->build())
+        ->toThrow(Exception::class, 'Silq Exception: Unable to use specified CA Certificate');
+});
+
+test('resume TLS sessions across connections', function () {
+    global $mtlsData;
+
+    $client = HttpClient::builder()
+        ->withServerAuthentication(CertificateAuthority::fromPem($mtlsData['ca']))
+        ->withClientAuthentication(ClientIdentity::fromPem($mtlsData['cert'], $mtlsData['key']))
+        ->withPoolMaxIdlePerHost(0)
+        ->build();
+
+    $reused = array_map(
+        fn() => $client->get('https://localhost:8444/tls-session')->send()->getText(),
+        range(1, 3),
+    );
+    expect($reused)->toBe(['.', 'r', 'r']);
+});
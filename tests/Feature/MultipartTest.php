@@ -0,0 +1,98 @@
+<?php
+use Silq\Multipart;
+
+test('send text fields', function () {
+    $multipart = (new Multipart())
+        ->withField('name', 'Silq')
+        ->withField('description', "Some content\non multiple lines");
+    $boundary = $multipart->getBoundary();
+    $json = sendToUnixServer(fn ($request) => $request->withMultipart($multipart));
+
+    expect($json['headers']['content-type'])->toBe("multipart/form-data; boundary=$boundary");
+    expect(base64_decode($json['body']))->toBe(
+        "--$boundary\r\n"
+        . "Content-Disposition: form-data; name=\"name\"\r\n\r\n"
+        . "Silq\r\n"
+        . "--$boundary\r\n"
+        . "Content-Disposition: form-data; name=\"description\"\r\n\r\n"
+        . "Some content\non multiple lines\r\n"
+        . "--$boundary--\r\n"
+    );
+    expect($json['headers']['content-length'])->toBe((string) strlen(base64_decode($json['body'])));
+});
+
+test('send file from disk', function () {
+    $file = tempnam(sys_get_temp_dir(), 'silq');
+    $contents = random_bytes(200 * 1024);
+    file_put_contents($file, $contents);
+
+    $multipart = (new Multipart())
+        ->withField('name', 'upload')
+        ->withFile('file', $file, 'data.bin');
+    $boundary = $multipart->getBoundary();
+    $json = sendToUnixServer(fn ($request) => $request->withMultipart($multipart));
+    unlink($file);
+
+    $body = base64_decode($json['body']);
+    expect($json['headers']['content-length'])->toBe((string) strlen($body));
+    expect($json['headers'])->not->toHaveKey('transfer-encoding');
+    expect($body)->toBe(
+        "--$boundary\r\n"
+        . "Content-Disposition: form-data; name=\"name\"\r\n\r\n"
+        . "upload\r\n"
+        . "--$boundary\r\n"
+        . "Content-Disposition: form-data; name=\"file\"; filename=\"data.bin\"\r\n"
+        . "Content-Type: application/octet-stream\r\n\r\n"
+        . "$contents\r\n"
+        . "--$boundary--\r\n"
+    );
+});
+
+test('default file name to the path one', function () {
+    $file = sys_get_temp_dir() . '/silq-report-' . getmypid() . '.csv';
+    file_put_contents($file, "a,b\n1,2\n");
+
+    $multipart = (new Multipart())->withFile('report', $file, null, 'text/csv');
+    $json = sendToUnixServer(fn ($request) => $request->withMultipart($multipart));
+    unlink($file);
+
+    $body = base64_decode($json['body']);
+    expect($body)->toContain('Content-Disposition: form-data; name="report"; filename="' . basename($file) . "\"\r\nContent-Type: text/csv\r\n\r\na,b\n1,2\n\r\n");
+});
+
+test('send file contents', function () {
+    $multipart = (new Multipart())
+        ->withFileContents('avatar', "\x89PNG", 'avatar "me".png', 'image/png');
+    $boundary = $multipart->getBoundary();
+    $json = sendToUnixServer(fn ($request) => $request->withMultipart($multipart));
+
+    expect(base64_decode($json['body']))->toBe(
+        "--$boundary\r\n"
+        . "Content-Disposition: form-data; name=\"avatar\"; filename=\"avatar %22me%22.png\"\r\n"
+        . "Content-Type: image/png\r\n\r\n"
+        . "\x89PNG\r\n"
+        . "--$boundary--\r\n"
+    );
+});
+
+test('compress multipart body', function () {
+    $file = tempnam(sys_get_temp_dir(), 'silq');
+    file_put_contents($file, str_repeat('compressible ', 10000));
+
+    $multipart = (new Multipart())->withFile('file', $file);
+    $json = sendToUnixServer(fn ($request) => $request->withMultipart($multipart)->withCompression('gzip'));
+    unlink($file);
+
+    expect($json['headers']['content-encoding'])->toBe('gzip');
+    expect($json['headers']['transfer-encoding'])->toBe('chunked');
+    expect(gzdecode(base64_decode($json['body'])))->toContain(str_repeat('compressible ', 10000));
+});
+
+test('generate distinct boundaries', function () {
+    expect((new Multipart())->getBoundary())->not->toBe((new Multipart())->getBoundary());
+});
+
+test('reject missing file', function () {
+    expect(fn () => (new Multipart())->withFile('file', '/does/not/exist'))
+        ->toThrow(Exception::class, 'Silq Exception: Unable to read file /does/not/exist: not a file');
+});
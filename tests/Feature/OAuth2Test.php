@@ -0,0 +1,122 @@
+<?php
+use Silq\HttpClient;
+
+beforeAll(function () {
+    putenv('SILQ_OAUTH_STATE=' . sys_get_temp_dir() . '/silq-test-oauth-' . getmypid() . '.json');
+});
+
+beforeEach(function () {
+    @unlink(getenv('SILQ_OAUTH_STATE'));
+    $this->server = proc_open([PHP_BINARY, '-S', 'localhost:8090', 'tests/data/oauth-server.php'], [], $pipes);
+    for ($i = 0; $i < 100 && !($socket = @fsockopen('localhost', 8090)); $i++) {
+        usleep(10000);
+    }
+    fclose($socket);
+    $this->builder = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withOAuth2ClientCredentials('http://localhost:8090/token', 'client', 'secret', ['read', 'write']);
+    $this->client = $this->builder->build();
+});
+
+afterEach(function () {
+    proc_terminate($this->server);
+    proc_close($this->server);
+    @unlink(getenv('SILQ_OAUTH_STATE'));
+});
+
+test('send bearer tokens', function () {
+    $response = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->build()
+        ->get('http://localhost:8080')
+        ->withBearerToken('my-token')
+        ->send();
+
+    expect($response->getJson()['headers']['authorization'])->toBe('Bearer my-token');
+});
+
+test('authenticate with client credentials', function () {
+    $json = $this->client->get('http://localhost:8090/resource')->send()->getJson();
+
+    expect($json)->toBe(['token' => 'token-1', 'issued' => 1, 'scopes' => ['read write']]);
+});
+
+test('cache tokens across requests and clients', function () {
+    $this->client->get('http://localhost:8090/resource')->send();
+    $this->client->get('http://localhost:8090/resource')->send();
+    $json = $this->builder->build()->get('http://localhost:8090/resource')->send()->getJson();
+
+    expect($json['issued'])->toBe(1);
+});
+
+test('renew tokens shortly before they expire', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withOAuth2ClientCredentials('http://localhost:8090/token?expires_in=2', 'client', 'secret')
+        ->build();
+
+    expect($client->get('http://localhost:8090/resource')->send()->getJson()['token'])->toBe('token-1');
+    expect($client->get('http://localhost:8090/resource')->send()->getJson()['token'])->toBe('token-1');
+    usleep(1100000);
+    expect($client->get('http://localhost:8090/resource')->send()->getJson()['token'])->toBe('token-2');
+});
+
+test('fetch another token once rejected', function () {
+    $this->client->get('http://localhost:8090/resource')->send();
+    $this->client->post('http://localhost:8090/revoke')->send();
+
+    $response = $this->client->get('http://localhost:8090/resource')->send();
+
+    expect($response->getStatusCode())->toBe(200);
+    expect($response->getJson()['token'])->toBe('token-2');
+});
+
+test('authenticate with rotated refresh tokens', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withOAuth2RefreshToken('http://localhost:8090/token', 'refresh-0', 'public')
+        ->build();
+
+    expect($client->get('http://localhost:8090/resource')->send()->getJson()['token'])->toBe('token-1');
+    $client->post('http://localhost:8090/revoke')->send();
+    expect($client->get('http://localhost:8090/resource')->send()->getJson()['token'])->toBe('token-2');
+});
+
+test('send tokens to the token endpoint origin only', function () {
+    $json = $this->client->get('http://localhost:8080/')->send()->getJson();
+
+    expect($json['headers'])->not->toHaveKey('authorization');
+});
+
+test('send tokens to the scope origins', function () {
+    $client = $this->builder->withOAuth2ScopeOrigins(['http://localhost:8080'])->build();
+
+    $json = $client->get('http://localhost:8080/')->send()->getJson();
+    expect($json['headers']['authorization'])->toBe('Bearer token-1');
+
+    $response = $client->get('http://localhost:8090/resource')->send();
+    expect($response->getStatusCode())->toBe(401);
+});
+
+test('reject invalid scope origins', function () {
+    expect(fn () => $this->builder->withOAuth2ScopeOrigins(['/relative']))
+        ->toThrow(Exception::class, "Silq Exception: Invalid OAuth 2 scope origin '/relative', expected e.g. https://api.example.com");
+});
+
+test('prefer the request own authorization', function () {
+    $response = $this->client->get('http://localhost:8090/resource')
+        ->withBearerToken('my-token')
+        ->send();
+
+    expect($response->getStatusCode())->toBe(401);
+});
+
+test('report token endpoint errors', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withOAuth2ClientCredentials('http://localhost:8090/token', 'client', 'wrong')
+        ->build();
+
+    expect(fn () => $client->get('http://localhost:8090/resource')->send())
+        ->toThrow(Exception::class, 'Silq Exception: Token endpoint refused the request: invalid_client: Unknown client');
+});
@@ -0,0 +1,120 @@
+<?php
+use Silq\HttpClient;
+use Silq\CertificateAuthority;
+
+test('return redirect responses as-is by default', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->build();
+    $response = $client->get('http://localhost:8082/302')->send();
+
+    expect($response->getStatusCode())->toBe(302);
+    expect($response->getHeaderFirstValue('location'))->toBe('http://localhost:8080/redirected');
+    expect($response->getUri())->toBe('http://localhost:8082/302');
+    expect($response->getRedirects())->toBe([]);
+});
+
+test('follow redirects and record the chain', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withRedirectPolicy(5)
+        ->build();
+    $response = $client->get('http://localhost:8082/relative/first')->send();
+
+    expect($response->getStatusCode())->toBe(200);
+    expect($response->getJson()['path'])->toBe('/redirected');
+    expect($response->getUri())->toBe('http://localhost:8080/redirected');
+    expect($response->getRedirects())->toBe([
+        'http://localhost:8082/relative/first',
+        'http://localhost:8082/302',
+    ]);
+});
+
+test('follow redirects to raw UTF-8 locations', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withRedirectPolicy(5)
+        ->build();
+    $response = $client->get('http://localhost:8082/utf8')->send();
+
+    expect($response->getStatusCode())->toBe(200);
+    expect($response->getUri())->toBe('http://localhost:8080/caf%C3%A9%20au%20lait');
+});
+
+test('rewrite method and body depending on redirect status', function (int $status, string $method, string $body) {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withRedirectPolicy(5)
+        ->build();
+    $json = $client->post("http://localhost:8082/$status")
+        ->withBody('payload')
+        ->send()
+        ->getJson();
+
+    expect($json['method'])->toBe($method);
+    expect($json['body'])->toBe($body);
+})->with([
+    [301, 'GET', ''],
+    [302, 'GET', ''],
+    [303, 'GET', ''],
+    [307, 'POST', 'payload'],
+    [308, 'POST', 'payload'],
+]);
+
+test('strip credentials on cross-origin redirects', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withRedirectPolicy(5)
+        ->build();
+    $json = $client->get('http://localhost:8082/302')
+        ->withBasicAuth('user', 'password')
+        ->withSafeCookies(['session' => 'secret'])
+        ->withHeaders(['x-custom' => 'kept'])
+        ->send()
+        ->getJson();
+
+    expect($json['headers'])->not->toHaveKey('authorization');
+    expect($json['headers'])->not->toHaveKey('cookie');
+    expect($json['headers']['x-custom'])->toBe('kept');
+    expect($json['headers']['host'])->toBe('localhost:8080');
+});
+
+test('fail past the maximum number of redirects', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withRedirectPolicy(3)
+        ->build();
+
+    expect(fn() => $client->get('http://localhost:8082/loop')->send())
+        ->toThrow(Exception::class, 'Silq Exception: Too many redirects, stopped after 3');
+});
+
+test('decide which redirects to follow with callable', function () {
+    $calls = [];
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->build();
+    $response = $client->get('http://localhost:8082/relative/first')
+        ->withRedirectPolicy(function (string $uri, int $status, array $redirects) use (&$calls) {
+            $calls[] = [$uri, $status, $redirects];
+            return count($redirects) < 1;
+        })
+        ->send();
+
+    expect($response->getStatusCode())->toBe(302);
+    expect($response->getUri())->toBe('http://localhost:8082/302');
+    expect($calls)->toBe([
+        ['http://localhost:8082/302', 302, []],
+        ['http://localhost:8080/redirected', 302, ['http://localhost:8082/relative/first']],
+    ]);
+});
+
+test('refuse redirects from HTTPS to unsecure HTTP', function () {
+    $client = HttpClient::builder()
+        ->withServerAuthentication(CertificateAuthority::fromPem(file_get_contents('tests/data/ca-crt.pem')))
+        ->withRedirectPolicy(5)
+        ->build();
+
+    expect(fn() => $client->get('https://localhost:8444/302')->send())
+        ->toThrow(Exception::class, 'Silq Exception: Refusing to follow redirect from HTTPS to unsecure HTTP');
+});
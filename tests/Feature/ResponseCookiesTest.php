@@ -0,0 +1,72 @@
+<?php
+use Silq\Cookie;
+use Silq\HttpClient;
+
+beforeEach(function () {
+    $this->client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->build();
+});
+
+test('parse cookies set by the response', function () {
+    $cookies = $this->client->get('http://localhost:8082/cookies/set')->send()->getCookies();
+
+    expect($cookies)->toHaveCount(4);
+    expect($cookies[0])->toBeInstanceOf(Cookie::class);
+    expect($cookies[0]->getName())->toBe('session');
+    expect($cookies[0]->getValue())->toBe('abc123');
+    expect($cookies[0]->getPath())->toBe('/');
+    expect($cookies[0]->getDomain())->toBeNull();
+    expect($cookies[0]->getExpires())->toBeNull();
+    expect($cookies[0]->isHttpOnly())->toBeTrue();
+    expect($cookies[0]->isSecure())->toBeFalse();
+    expect($cookies[1]->getName())->toBe('theme');
+    expect($cookies[1]->getMaxAge())->toBe(3600);
+    expect($cookies[1]->getPath())->toBeNull();
+    expect($cookies[3]->getName())->toBe('secure');
+    expect($cookies[3]->isSecure())->toBeTrue();
+});
+
+test('parse cookies without a jar storing them', function () {
+    $response = $this->client->get('http://localhost:8082/cookies/set')->send();
+
+    expect($response->getCookieErrors())->toBe([]);
+    expect(array_map(fn ($cookie) => $cookie->getName(), $response->getCookies()))
+        ->toBe(['session', 'theme', 'scoped', 'secure']);
+});
+
+test('parse cookies leniently, collecting errors', function () {
+    $response = $this->client->get('http://localhost:8082/cookies/malformed')->send();
+    $cookies = $response->getCookies();
+
+    expect($cookies)->toHaveCount(2);
+    [$lenient, $dated] = $cookies;
+    expect($lenient->getName())->toBe('lenient');
+    expect($lenient->getExpires())->toBeNull();
+    expect($lenient->getMaxAge())->toBeNull();
+    expect($lenient->getSameSite())->toBeNull();
+    expect($lenient->getPath())->toBeNull();
+    expect($lenient->getDomain())->toBe('example.com');
+    expect($lenient->isSecure())->toBeTrue();
+    expect($lenient->isPartitioned())->toBeTrue();
+
+    expect($dated->getExpires())->toBe(1445412480);
+    expect($dated->getMaxAge())->toBe(60);
+    expect($dated->getSameSite())->toBe('Lax');
+    expect($dated->isPartitioned())->toBeFalse();
+
+    expect($response->getCookieErrors())->toBe([
+        'Invalid cookie "no-equal-sign": missing \'=\'',
+        'Invalid cookie "=no-name": empty name',
+        'Invalid Expires attribute of cookie lenient: "not a date"',
+        'Invalid Max-Age attribute of cookie lenient: "abc"',
+        'Invalid SameSite attribute of cookie lenient: "Weird"',
+    ]);
+});
+
+test('return no cookies when none are set', function () {
+    $response = $this->client->get('http://localhost:8080')->send();
+
+    expect($response->getCookies())->toBe([]);
+    expect($response->getCookieErrors())->toBe([]);
+});
@@ -0,0 +1,98 @@
+<?php
+use Silq\HttpClient;
+use Silq\RetryPolicy;
+
+test('make a single attempt by default', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->build();
+    $response = $client->get('http://localhost:8080/')
+        ->withHeaders(['x-set-response-status-code' => '503'])
+        ->send();
+
+    expect($response->getStatusCode())->toBe(503);
+    expect($response->getAttempts())->toBe(1);
+});
+
+test('retry responses with retryable status', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withRetryPolicy((new RetryPolicy(3))->withBackoff(0.01, 0.05))
+        ->build();
+    $response = $client->get('http://localhost:8080/')
+        ->withHeaders(['x-set-response-status-code' => '503'])
+        ->send();
+
+    expect($response->getStatusCode())->toBe(503);
+    expect($response->getAttempts())->toBe(3);
+});
+
+test('do not retry successful responses', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withRetryPolicy(new RetryPolicy(3))
+        ->build();
+    $response = $client->get('http://localhost:8080/')->send();
+
+    expect($response->getStatusCode())->toBe(200);
+    expect($response->getAttempts())->toBe(1);
+});
+
+test('retry non-idempotent methods only when allowed', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->build();
+    $policy = (new RetryPolicy(2))->withBackoff(0.01, 0.05)->withStatuses([500]);
+
+    $response = $client->post('http://localhost:8080/')
+        ->withHeaders(['x-set-response-status-code' => '500'])
+        ->withBody('payload')
+        ->withRetryPolicy($policy)
+        ->send();
+    expect($response->getAttempts())->toBe(1);
+
+    $response = $client->post('http://localhost:8080/')
+        ->withHeaders(['x-set-response-status-code' => '500'])
+        ->withBody('payload')
+        ->withRetryPolicy($policy->withNonIdempotentMethods(true))
+        ->send();
+    expect($response->getAttempts())->toBe(2);
+    expect($response->getJson()['body'])->toBe('payload');
+});
+
+test('retry connection failures', function () {
+    $resolutions = 0;
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withDnsResolver(function (string $host) use (&$resolutions) {
+            $resolutions++;
+            return '127.0.0.1';
+        })
+        ->withRetryPolicy((new RetryPolicy(3))->withBackoff(0.01, 0.05))
+        ->build();
+
+    expect(fn() => $client->get('http://silq.test:1/')->send())
+        ->toThrow(Exception::class, 'Silq Exception: Unable to establish connection');
+    expect($resolutions)->toBe(3);
+});
+
+test('do not retry errors left out of the policy', function () {
+    $resolutions = 0;
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withDnsResolver(function (string $host) use (&$resolutions) {
+            $resolutions++;
+            return '127.0.0.1';
+        })
+        ->withRetryPolicy((new RetryPolicy(3))->withErrors(['timeout']))
+        ->build();
+
+    expect(fn() => $client->get('http://silq.test:1/')->send())
+        ->toThrow(Exception::class, 'Silq Exception: Unable to establish connection');
+    expect($resolutions)->toBe(1);
+});
+
+test('reject unknown retryable error', function () {
+    expect(fn() => (new RetryPolicy(3))->withErrors(['unknown']))
+        ->toThrow(Exception::class, 'Silq Exception: Unknown retryable error: unknown');
+});
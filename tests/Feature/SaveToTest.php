@@ -0,0 +1,84 @@
+<?php
+use Silq\HttpClient;
+
+beforeEach(function () {
+    $this->body = file_get_contents('tests/data/encoded/body.json');
+    $this->path = sys_get_temp_dir() . '/silq-download-' . getmypid() . '.json';
+    $this->client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->build();
+});
+
+afterEach(function () {
+    @unlink($this->path);
+});
+
+function partialFiles(string $path): array
+{
+    return glob(dirname($path) . '/.' . basename($path) . '.*.part');
+}
+
+test('save body to file', function () {
+    file_put_contents($this->path, 'previous content, longer than the body' . str_repeat('.', 10000));
+
+    $written = $this->client->get('http://localhost:8083/plain')->send()->saveTo($this->path);
+
+    expect($written)->toBe(strlen($this->body));
+    expect(file_get_contents($this->path))->toBe($this->body);
+});
+
+test('save decoded body to file', function () {
+    $written = $this->client->get('http://localhost:8083/gzip')->send()->saveTo($this->path);
+
+    expect($written)->toBe(strlen($this->body));
+    expect(file_get_contents($this->path))->toBe($this->body);
+});
+
+test('save body atomically', function () {
+    $written = $this->client->get('http://localhost:8083/plain')->send()->saveTo($this->path, true);
+
+    expect($written)->toBe(strlen($this->body));
+    expect(file_get_contents($this->path))->toBe($this->body);
+    expect(partialFiles($this->path))->toBe([]);
+});
+
+test('append body to file', function () {
+    file_put_contents($this->path, 'start:');
+
+    $written = $this->client->get('http://localhost:8083/plain')->send()->saveTo($this->path, false, true);
+
+    expect($written)->toBe(strlen($this->body));
+    expect(file_get_contents($this->path))->toBe('start:' . $this->body);
+});
+
+test('fail past the maximum size', function () {
+    $response = $this->client->get('http://localhost:8083/plain')->send();
+
+    expect(fn () => $response->saveTo($this->path, false, false, 10))
+        ->toThrow(Exception::class, 'Silq Exception: Body exceeds the maximum size of 10 bytes');
+});
+
+test('leave target untouched when atomic save fails', function () {
+    file_put_contents($this->path, 'previous content');
+    $response = $this->client->get('http://localhost:8083/plain')->send();
+
+    expect(fn () => $response->saveTo($this->path, true, false, 10))
+        ->toThrow(Exception::class, 'Silq Exception: Body exceeds the maximum size of 10 bytes');
+    expect(file_get_contents($this->path))->toBe('previous content');
+    expect(partialFiles($this->path))->toBe([]);
+});
+
+test('reject atomic append', function () {
+    $response = $this->client->get('http://localhost:8083/plain')->send();
+
+    expect(fn () => $response->saveTo($this->path, true, true))
+        ->toThrow(Exception::class, "Silq Exception: Atomic and append modes can't be combined");
+});
+
+test('consume the body', function () {
+    $response = $this->client->get('http://localhost:8083/plain')->send();
+    $response->saveTo($this->path);
+
+    expect(fn () => $response->getBytes())
+        ->toThrow(Exception::class, 'Silq Exception: Body already consumed');
+});
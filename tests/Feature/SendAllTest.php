@@ -0,0 +1,90 @@
+<?php
+use Silq\HttpClient;
+use Silq\Response;
+use Silq\TimeoutException;
+
+beforeEach(function () {
+    $this->client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->build();
+});
+
+test('send requests concurrently preserving keys', function () {
+    $responses = $this->client->sendAll([
+        'first' => $this->client->get('http://localhost:8080/first'),
+        7 => $this->client->post('http://localhost:8080/second'),
+        'third' => $this->client->get('http://localhost:8080/third'),
+    ]);
+
+    expect(array_keys($responses))->toBe(['first', 7, 'third']);
+    expect($responses['first'])->toBeInstanceOf(Response::class);
+    expect($responses['first']->getJson()['path'])->toBe('/first');
+    expect($responses[7]->getJson()['method'])->toBe('POST');
+    expect($responses[7]->getJson()['path'])->toBe('/second');
+    expect($responses['third']->getJson()['path'])->toBe('/third');
+});
+
+test('run requests in parallel', function () {
+    $requests = array_map(
+        fn () => $this->client->get('http://localhost:8080')->withHeaders(['x-set-response-delay-ms' => '300']),
+        range(1, 5),
+    );
+
+    $start = microtime(true);
+    $responses = $this->client->sendAll($requests);
+
+    expect(microtime(true) - $start)->toBeLessThan(1.0);
+    expect(array_map(fn ($response) => $response->getStatusCode(), $responses))->toBe([200, 200, 200, 200, 200]);
+});
+
+test('limit the number of requests in flight', function () {
+    $requests = array_map(
+        fn () => $this->client->get('http://localhost:8080')->withHeaders(['x-set-response-delay-ms' => '200']),
+        range(1, 4),
+    );
+
+    $start = microtime(true);
+    $responses = $this->client->sendAll($requests, 2);
+
+    expect(microtime(true) - $start)->toBeGreaterThan(0.4);
+    expect($responses)->toHaveCount(4);
+});
+
+test('return exceptions of failed requests', function () {
+    $responses = $this->client->sendAll([
+        'ok' => $this->client->get('http://localhost:8080'),
+        'refused' => $this->client->get('http://localhost:1'),
+    ]);
+
+    expect($responses['ok'])->toBeInstanceOf(Response::class);
+    expect($responses['refused'])->toBeInstanceOf(Exception::class);
+    expect($responses['refused']->getMessage())->toStartWith('Silq Exception: ');
+});
+
+test('fail requests not completed before the timeout', function () {
+    $responses = $this->client->sendAll([
+        'fast' => $this->client->get('http://localhost:8080'),
+        'slow' => $this->client->get('http://localhost:8080')->withHeaders(['x-set-response-delay-ms' => '2000']),
+    ], null, 0.5);
+
+    expect($responses['fast'])->toBeInstanceOf(Response::class);
+    expect($responses['slow'])->toBeInstanceOf(TimeoutException::class);
+    expect($responses['slow']->getCode())->toBe(TimeoutException::DEADLINE);
+});
+
+test('return an empty array for no requests', function () {
+    expect($this->client->sendAll([]))->toBe([]);
+});
+
+test('reject invalid requests', function () {
+    $request = $this->client->get('http://localhost:8080');
+
+    expect(fn () => $this->client->sendAll(['not a request']))
+        ->toThrow(Exception::class, 'Silq Exception: Requests must be RequestBuilder objects');
+    expect(fn () => $this->client->sendAll([new stdClass()]))
+        ->toThrow(Exception::class, 'Silq Exception: Requests must be RequestBuilder objects');
+    expect(fn () => $this->client->sendAll([$request, $request]))
+        ->toThrow(Exception::class, "Silq Exception: The same RequestBuilder can't be sent twice");
+    expect(fn () => $this->client->sendAll([$request], 0))
+        ->toThrow(Exception::class, 'Silq Exception: Concurrency must be at least 1');
+});
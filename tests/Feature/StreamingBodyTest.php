@@ -0,0 +1,194 @@
+<?php
+use Silq\HttpClient;
+use Silq\RetryPolicy;
+
+test('stream body from file', function () {
+    $file = tempnam(sys_get_temp_dir(), 'silq');
+    $contents = random_bytes(300 * 1024);
+    file_put_contents($file, $contents);
+
+    $json = sendToUnixServer(fn ($request) => $request->withBodyFromFile($file));
+    unlink($file);
+
+    expect($json['headers']['content-length'])->toBe((string) strlen($contents));
+    expect($json['headers'])->not->toHaveKey('transfer-encoding');
+    expect(base64_decode($json['body']))->toBe($contents);
+});
+
+test('stream body from PHP stream', function () {
+    $stream = fopen('php://memory', 'r+');
+    fwrite($stream, 'skipped' . str_repeat('streamed content ', 10000));
+    fseek($stream, strlen('skipped'));
+
+    $json = sendToUnixServer(fn ($request) => $request->withBodyFromStream($stream));
+    fclose($stream);
+
+    expect($json['headers']['transfer-encoding'])->toBe('chunked');
+    expect($json['headers'])->not->toHaveKey('content-length');
+    expect(base64_decode($json['body']))->toBe(str_repeat('streamed content ', 10000));
+});
+
+test('stream body from PHP stream with known length', function () {
+    $stream = fopen('php://memory', 'r+');
+    fwrite($stream, 'first part, second part');
+    rewind($stream);
+
+    $json = sendToUnixServer(fn ($request) => $request->withBodyFromStream($stream, 10));
+    fclose($stream);
+
+    expect($json['headers']['content-length'])->toBe('10');
+    expect(base64_decode($json['body']))->toBe('first part');
+});
+
+test('read PHP streams without blocking', function () {
+    $process = proc_open(['sh', '-c', 'sleep 0.2; printf "piped content"'], [1 => ['pipe', 'w']], $pipes);
+
+    $json = sendToUnixServer(fn ($request) => $request->withBodyFromStream($pipes[1]));
+
+    expect(stream_get_meta_data($pipes[1])['blocked'])->toBeFalse();
+    expect(base64_decode($json['body']))->toBe('piped content');
+    fclose($pipes[1]);
+    proc_close($process);
+});
+
+test('stream body from generator', function () {
+    $chunks = (function () {
+        foreach (range(1, 100) as $i) {
+            yield "chunk $i\n";
+        }
+    })();
+
+    $json = sendToUnixServer(fn ($request) => $request->withBodyFromGenerator($chunks));
+
+    expect($json['headers']['transfer-encoding'])->toBe('chunked');
+    expect(base64_decode($json['body']))->toBe(implode('', array_map(fn ($i) => "chunk $i\n", range(1, 100))));
+});
+
+test('stream body from callable', function () {
+    $chunks = ['first ', '', 'second'];
+    $json = sendToUnixServer(fn ($request) => $request
+        ->withBodyFromGenerator(function () use (&$chunks) {
+            return array_shift($chunks);
+        }, 6));
+
+    // An empty string ends the body as well
+    expect($json['headers']['content-length'])->toBe('6');
+    expect(base64_decode($json['body']))->toBe('first ');
+});
+
+test('compress streamed body', function () {
+    $chunks = (function () {
+        foreach (range(1, 100) as $i) {
+            yield str_repeat('compressible ', 100);
+        }
+    })();
+
+    $json = sendToUnixServer(fn ($request) => $request
+        ->withBodyFromGenerator($chunks, 130000)
+        ->withCompression('gzip'));
+
+    expect($json['headers']['content-encoding'])->toBe('gzip');
+    expect($json['headers']['transfer-encoding'])->toBe('chunked');
+    expect(gzdecode(base64_decode($json['body'])))->toBe(str_repeat('compressible ', 10000));
+});
+
+test('fail when the generator produces less than announced', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->build();
+    $chunks = (function () {
+        yield 'short';
+    })();
+    $request = $client->post('http://localhost:8080/')->withBodyFromGenerator($chunks, 100);
+
+    expect(fn () => $request->send())
+        ->toThrow(Exception::class, 'Silq Exception: Body is shorter than its announced length of 100 bytes');
+});
+
+test('fail when the generator produces more than announced', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->build();
+    $chunks = (function () {
+        yield 'longer than announced';
+    })();
+    $request = $client->post('http://localhost:8080/')->withBodyFromGenerator($chunks, 6);
+
+    expect(fn () => $request->send())
+        ->toThrow(Exception::class, 'Silq Exception: Body is longer than its announced length of 6 bytes');
+});
+
+test('fail when the generator yields non-strings', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->build();
+    $chunks = (function () {
+        yield ['not a string'];
+    })();
+    $request = $client->post('http://localhost:8080/')->withBodyFromGenerator($chunks);
+
+    expect(fn () => $request->send())
+        ->toThrow(Exception::class, 'Silq Exception: Body generator must produce strings');
+});
+
+test('do not retry streamed bodies', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withRetryPolicy((new RetryPolicy(3))->withBackoff(0.01, 0.05)->withNonIdempotentMethods(true))
+        ->build();
+    $chunks = (function () {
+        yield 'payload';
+    })();
+    $response = $client->post('http://localhost:8080/')
+        ->withHeaders(['x-set-response-status-code' => '503'])
+        ->withBodyFromGenerator($chunks)
+        ->send();
+
+    expect($response->getStatusCode())->toBe(503);
+    expect($response->getAttempts())->toBe(1);
+});
+
+test('return redirects requiring a streamed body as-is', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withRedirectPolicy(5)
+        ->build();
+    $chunks = (function () {
+        yield 'payload';
+    })();
+    $response = $client->post('http://localhost:8082/307')
+        ->withBodyFromGenerator($chunks)
+        ->send();
+
+    expect($response->getStatusCode())->toBe(307);
+    expect($response->getRedirects())->toBe([]);
+});
+
+test('follow redirects dropping a streamed body', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withRedirectPolicy(5)
+        ->build();
+    $chunks = (function () {
+        yield 'payload';
+    })();
+    $json = $client->post('http://localhost:8082/303')
+        ->withBodyFromGenerator($chunks)
+        ->send()
+        ->getJson();
+
+    expect($json['method'])->toBe('GET');
+    expect($json['body'])->toBe('');
+});
+
+test('reject invalid sources', function () {
+    $client = HttpClient::builder()->build();
+    $request = $client->post('https://localhost:8443/');
+
+    expect(fn () => $request->withBodyFromStream('not a stream'))
+        ->toThrow(Exception::class, 'Silq Exception: Body stream must be a stream resource');
+    expect(fn () => $request->withBodyFromGenerator('not a generator'))
+        ->toThrow(Exception::class, 'Silq Exception: Body generator must be a Generator or a callable');
+    expect(fn () => $request->withBodyFromFile('/does/not/exist'))
+        ->toThrow(Exception::class, 'Silq Exception: Unable to read file /does/not/exist: not a file');
+});
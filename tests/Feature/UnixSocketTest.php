@@ -0,0 +1,66 @@
+<?php
+use Silq\HttpClient;
+
+test('send request over Unix domain socket', function () {
+    $path = sys_get_temp_dir() . '/silq-test-' . getmypid() . '.sock';
+    $server = startUnixServer($path);
+
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->build();
+    $response = $client->get('http://docker/v1.43/containers/json?all=1')
+        ->withUnixSocket($path)
+        ->send();
+
+    expect($response->getStatusCode())->toBe(200);
+    $json = $response->getJson();
+    expect($json['request'])->toBe('GET /v1.43/containers/json?all=1 HTTP/1.1');
+    expect($json['headers']['host'])->toBe('docker');
+
+    proc_close($server);
+});
+
+test('send requests over client-wide Unix domain socket', function () {
+    $path = sys_get_temp_dir() . '/silq-test-client-' . getmypid() . '.sock';
+    $server = startUnixServer($path);
+
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withUnixSocket($path)
+        ->withProxy('http://localhost:1')
+        ->build();
+    $response = $client->get('http://localhost/status')->send();
+
+    expect($response->getStatusCode())->toBe(200);
+    expect($response->getJson()['request'])->toBe('GET /status HTTP/1.1');
+
+    proc_close($server);
+});
+
+test('follow redirects to other origins over TCP', function () {
+    $path = sys_get_temp_dir() . '/silq-test-redirect-' . getmypid() . '.sock';
+    $server = startUnixServer($path);
+
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->withRedirectPolicy(1)
+        ->build();
+    $location = urlencode('http://localhost:8080/redirected');
+    $response = $client->get("http://localhost/redirect?location=$location")
+        ->withUnixSocket($path)
+        ->send();
+
+    expect($response->getStatusCode())->toBe(200);
+    expect($response->getJson()['path'])->toBe('/redirected');
+
+    proc_close($server);
+});
+
+test('raise exception when Unix domain socket does not exist', function () {
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->build();
+
+    expect(fn() => $client->get('http://localhost/')->withUnixSocket('/nonexistent/silq.sock')->send())
+        ->toThrow(Exception::class, 'Silq Exception: Unable to establish connection');
+});
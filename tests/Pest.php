@@ -1,4 +1,5 @@
 <?php
+use Silq\HttpClient;
 
 /*
 |--------------------------------------------------------------------------
@@ -43,3 +44,35 @@ function something()
 {
     // ..
 }
+
+/**
+ * Start the given server script listening on the socket at `$path`, followed by `$args`, and wait
+ * for the socket. By default, tests/data/unix-server.php answers a single request.
+ */
+function startUnixServer(string $path, string $script = 'tests/data/unix-server.php', array $args = [])
+{
+    $process = proc_open([PHP_BINARY, $script, $path, ...$args], [], $pipes);
+    for ($i = 0; $i < 100 && !file_exists($path); $i++) {
+        usleep(10000);
+    }
+    return $process;
+}
+
+/**
+ * Send a POST request built by `$build` to tests/data/unix-server.php, and return what the server
+ * received: the request line, headers and base64 encoded body.
+ */
+function sendToUnixServer(callable $build): array
+{
+    $path = sys_get_temp_dir() . '/silq-test-echo-' . getmypid() . '.sock';
+    $server = startUnixServer($path);
+
+    $client = HttpClient::builder()
+        ->allowUnsecureHttp(true)
+        ->build();
+    $request = $client->post('http://localhost/upload')->withUnixSocket($path);
+    $json = $build($request)->send()->getJson();
+
+    proc_close($server);
+    return $json;
+}
@@ -0,0 +1,101 @@
+<?php
+// Minimal HTTP/1.1 server protected by Digest authentication (RFC 7616) on a Unix domain socket,
+// closing each connection after its response. Authorized requests get the user, nonce count and
+// number of requests received so far as JSON.
+//
+// Usage: digest-server.php <path> <algorithms> <qop> [stale]
+// With `stale`, nonces are only valid for one request and answered with stale=true afterwards.
+[, $path, $algorithms, $qop] = $argv;
+$stale = ($argv[4] ?? '') === 'stale';
+$realm = 'silq@localhost';
+$password = 'secret';
+$hashes = ['MD5' => 'md5', 'SHA-256' => 'sha256', 'SHA-512-256' => 'sha512/256'];
+
+@unlink($path);
+$server = stream_socket_server("unix://$path", $errno, $errstr);
+if ($server === false) {
+    fwrite(STDERR, "$errstr\n");
+    exit(1);
+}
+
+$nonces = [];
+$requests = 0;
+while ($connection = @stream_socket_accept($server, 10)) {
+    $requests++;
+    [$method, $uri] = explode(' ', trim(fgets($connection)));
+    $headers = [];
+    while (($line = fgets($connection)) !== false && trim($line) !== '') {
+        [$name, $value] = explode(':', $line, 2);
+        $headers[strtolower(trim($name))] = trim($value);
+    }
+
+    $requestBody = '';
+    if (isset($headers['content-length'])) {
+        $length = (int) $headers['content-length'];
+        while (strlen($requestBody) < $length && !feof($connection)) {
+            $requestBody .= fread($connection, $length - strlen($requestBody));
+        }
+    } elseif (strtolower($headers['transfer-encoding'] ?? '') === 'chunked') {
+        while (($size = hexdec(trim(fgets($connection)))) > 0) {
+            $requestBody .= fread($connection, $size);
+            fgets($connection);
+        }
+        fgets($connection);
+    }
+
+    $params = [];
+    if (preg_match('/^Digest\s+(.*)$/i', $headers['authorization'] ?? '', $matches)) {
+        preg_match_all('/([\w*]+)=(?:"((?:[^"\\\\]|\\\\.)*)"|([^,\s]*))/', $matches[1], $pairs, PREG_SET_ORDER);
+        foreach ($pairs as $pair) {
+            $params[strtolower($pair[1])] = $pair[2] !== '' ? stripslashes($pair[2]) : ($pair[3] ?? '');
+        }
+    }
+
+    $authorized = false;
+    $expired = false;
+    $algorithm = preg_replace('/-sess$/i', '', $params['algorithm'] ?? 'MD5');
+    $hash = $hashes[$algorithm] ?? null;
+    $nonce = $params['nonce'] ?? '';
+    if ($hash !== null && isset($nonces[$nonce]) && ($params['uri'] ?? '') === $uri) {
+        $h = fn (string $data) => hash($hash, $data);
+        $nc = hexdec($params['nc'] ?? '0');
+        $user = $params['username'] ?? '';
+        if (($params['userhash'] ?? '') === 'true') {
+            $user = $user === $h("alice:$realm") ? 'alice' : '';
+        }
+        $ha1 = $h("$user:$realm:$password");
+        if (str_ends_with(strtolower($params['algorithm'] ?? ''), '-sess')) {
+            $ha1 = $h("$ha1:$nonce:{$params['cnonce']}");
+        }
+        $ha2 = ($params['qop'] ?? '') === 'auth-int'
+            ? $h("$method:$uri:" . $h($requestBody))
+            : $h("$method:$uri");
+        $expected = $h("$ha1:$nonce:{$params['nc']}:{$params['cnonce']}:{$params['qop']}:$ha2");
+        if (hash_equals($expected, $params['response'] ?? '') && $nc > $nonces[$nonce]) {
+            $expired = $stale && $nonces[$nonce] > 0;
+            $authorized = !$expired;
+            $nonces[$nonce] = $nc;
+        }
+    }
+
+    if ($authorized) {
+        $status = '200 OK';
+        $extra = '';
+        $body = json_encode(['user' => $user, 'nc' => $params['nc'], 'requests' => $requests]);
+    } else {
+        $status = '401 Unauthorized';
+        $extra = '';
+        foreach (explode(',', $algorithms) as $offered) {
+            $nonce = bin2hex(random_bytes(16));
+            $nonces[$nonce] = 0;
+            $extra .= "WWW-Authenticate: Digest realm=\"$realm\", qop=\"$qop\", algorithm=$offered, "
+                . "nonce=\"$nonce\", opaque=\"opaque\"" . ($expired ? ', stale=true' : '') . "\r\n";
+        }
+        $body = json_encode(['requests' => $requests]);
+    }
+    fwrite($connection, "HTTP/1.1 $status\r\n{$extra}Content-Type: application/json\r\nContent-Length: "
+        . strlen($body) . "\r\nConnection: close\r\n\r\n" . $body);
+    fclose($connection);
+}
+fclose($server);
+unlink($path);
@@ -0,0 +1,72 @@
+<?php
+// Router of the PHP built-in server acting as an OAuth 2 authorization and resource server:
+// - POST /token issues tokens for the client "client" with secret "secret", or the public client
+//   "public" with the current refresh token, rotated on each use. Tokens expire after the
+//   `expires_in` query parameter's seconds, one hour by default.
+// - /resource returns the bearer token it got and the number of tokens issued so far.
+// - POST /revoke revokes all the tokens issued.
+// State is kept in the file named by the SILQ_OAUTH_STATE environment variable.
+$file = getenv('SILQ_OAUTH_STATE');
+$state = json_decode(@file_get_contents($file) ?: '', true)
+    ?: ['issued' => 0, 'tokens' => [], 'refresh_token' => 'refresh-0', 'scopes' => []];
+
+function reply(int $status, array $body, array $headers = []): void
+{
+    http_response_code($status);
+    header('Content-Type: application/json');
+    foreach ($headers as $header) {
+        header($header);
+    }
+    echo json_encode($body);
+}
+
+switch (parse_url($_SERVER['REQUEST_URI'], PHP_URL_PATH)) {
+    case '/token':
+        $grant = $_POST['grant_type'] ?? '';
+        $confidential = ($_SERVER['HTTP_AUTHORIZATION'] ?? '') === 'Basic ' . base64_encode('client:secret');
+        if ($grant === 'client_credentials' && !$confidential) {
+            reply(401, ['error' => 'invalid_client', 'error_description' => 'Unknown client']);
+            break;
+        }
+        if ($grant === 'refresh_token' && ($_POST['refresh_token'] ?? '') !== $state['refresh_token']) {
+            reply(400, ['error' => 'invalid_grant']);
+            break;
+        }
+        if (!in_array($grant, ['client_credentials', 'refresh_token'])) {
+            reply(400, ['error' => 'unsupported_grant_type']);
+            break;
+        }
+
+        $state['issued']++;
+        $token = "token-{$state['issued']}";
+        $state['tokens'][] = $token;
+        $state['scopes'][] = $_POST['scope'] ?? null;
+        $response = [
+            'access_token' => $token,
+            'token_type' => 'Bearer',
+            'expires_in' => (int) ($_GET['expires_in'] ?? 3600),
+        ];
+        if ($grant === 'refresh_token') {
+            $state['refresh_token'] = $response['refresh_token'] = "refresh-{$state['issued']}";
+        }
+        reply(200, $response);
+        break;
+
+    case '/resource':
+        $token = preg_replace('/^Bearer /', '', $_SERVER['HTTP_AUTHORIZATION'] ?? '');
+        if (!in_array($token, $state['tokens'], true)) {
+            reply(401, ['error' => 'invalid_token'], ['WWW-Authenticate: Bearer error="invalid_token"']);
+            break;
+        }
+        reply(200, ['token' => $token, 'issued' => $state['issued'], 'scopes' => $state['scopes']]);
+        break;
+
+    case '/revoke':
+        $state['tokens'] = [];
+        reply(200, []);
+        break;
+
+    default:
+        reply(404, []);
+}
+file_put_contents($file, json_encode($state));
@@ -0,0 +1,38 @@
+<?php
+// Minimal HTTP/1.1 server on a Unix domain socket closing a kept-alive connection as it's reused:
+// the first connection answers one request, then reads the next one and closes without answering.
+// The second connection answers one request. Responses hold the number of their connection.
+//
+// Usage: stale-server.php <path>
+$path = $argv[1];
+@unlink($path);
+$server = stream_socket_server("unix://$path", $errno, $errstr);
+if ($server === false) {
+    fwrite(STDERR, "$errstr\n");
+    exit(1);
+}
+
+/** Read a request without body, up to the end of its headers. */
+function readRequest($connection): void
+{
+    while (($line = fgets($connection)) !== false && trim($line) !== '') {
+    }
+}
+
+function reply($connection, string $body): void
+{
+    fwrite($connection, "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: " . strlen($body) . "\r\n\r\n" . $body);
+}
+
+$connection = stream_socket_accept($server, 10);
+readRequest($connection);
+reply($connection, '1');
+readRequest($connection);
+fclose($connection);
+
+$connection = stream_socket_accept($server, 10);
+readRequest($connection);
+reply($connection, '2');
+fclose($connection);
+fclose($server);
+unlink($path);
@@ -0,0 +1,57 @@
+<?php
+// Minimal HTTP/1.1 server answering a single request on a Unix domain socket, with the received
+// request line, headers and base64 encoded body as JSON, or a redirect to the `location` query
+// parameter of `/redirect` requests.
+$path = $argv[1];
+@unlink($path);
+$server = stream_socket_server("unix://$path", $errno, $errstr);
+if ($server === false) {
+    fwrite(STDERR, "$errstr\n");
+    exit(1);
+}
+
+$connection = stream_socket_accept($server, 10);
+$requestLine = trim(fgets($connection));
+$headers = [];
+while (($line = fgets($connection)) !== false && trim($line) !== '') {
+    [$name, $value] = explode(':', $line, 2);
+    $headers[strtolower(trim($name))] = trim($value);
+}
+
+$requestBody = '';
+if (isset($headers['content-length'])) {
+    $length = (int) $headers['content-length'];
+    while (strlen($requestBody) < $length && !feof($connection)) {
+        $requestBody .= fread($connection, $length - strlen($requestBody));
+    }
+} elseif (strtolower($headers['transfer-encoding'] ?? '') === 'chunked') {
+    while (($size = hexdec(trim(fgets($connection)))) > 0) {
+        $chunk = '';
+        while (strlen($chunk) < $size && !feof($connection)) {
+            $chunk .= fread($connection, $size - strlen($chunk));
+        }
+        $requestBody .= $chunk;
+        fgets($connection);
+    }
+    // Trailers, up to the final empty line
+    while (($line = fgets($connection)) !== false && trim($line) !== '') {
+    }
+}
+
+if (preg_match('#^\S+ /redirect\?location=(\S+) #', $requestLine, $matches)) {
+    fwrite($connection, "HTTP/1.1 302 Found\r\nLocation: " . urldecode($matches[1]) . "\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+    fclose($connection);
+    fclose($server);
+    unlink($path);
+    exit;
+}
+
+$body = json_encode([
+    'request' => $requestLine,
+    'headers' => $headers,
+    'body' => base64_encode($requestBody),
+]);
+fwrite($connection, "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: " . strlen($body) . "\r\nConnection: close\r\n\r\n" . $body);
+fclose($connection);
+fclose($server);
+unlink($path);